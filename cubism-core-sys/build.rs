@@ -2,6 +2,13 @@ use std::env;
 use std::path::PathBuf;
 
 fn main() {
+    // the mock core is implemented in rust and the dynamic core is loaded at runtime,
+    // there is nothing to link against in either case
+    if env::var_os("CARGO_FEATURE_MOCK_CORE").is_some()
+        || env::var_os("CARGO_FEATURE_DYNAMIC").is_some()
+    {
+        return;
+    }
     let target = env::var("TARGET").unwrap();
     let mut target_s = target.split('-');
     let (arch, vendor, sys, _abi) = (
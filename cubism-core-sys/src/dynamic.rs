@@ -0,0 +1,96 @@
+//! Runtime loading of the Live2DCubismCore shared library.
+//!
+//! Enabled by the `dynamic` feature. Instead of linking against the core at build time, all
+//! functions of this crate call through a table of symbols that is resolved by [load_core](fn.load_core.html).
+//! Calling any core function before the library has been loaded panics.
+use libloading::Library;
+
+use std::ffi::OsStr;
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, Ordering};
+use std::sync::Mutex;
+
+use draw::DrawFunctions;
+use logging::LoggingFunctions;
+use moc::MocFunctions;
+use model::ModelFunctions;
+use {CoreFunctions, LoadError};
+
+/// The loaded library and all symbols resolved from it.
+pub struct Core {
+    pub core: CoreFunctions,
+    pub draw: DrawFunctions,
+    pub logging: LoggingFunctions,
+    pub moc: MocFunctions,
+    pub model: ModelFunctions,
+    _library: Library,
+}
+
+static CORE: AtomicPtr<Core> = AtomicPtr::new(ptr::null_mut());
+static LOAD_LOCK: Mutex<()> = Mutex::new(());
+
+/// Loads the core library at `path` and resolves all of its symbols.
+///
+/// The library can only be loaded once and is never unloaded, as mocs and models created
+/// from it may live until the end of the program.
+pub fn load_core<P: AsRef<OsStr>>(path: P) -> Result<(), LoadError> {
+    let _lock = LOAD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    if is_core_loaded() {
+        return Err(LoadError::AlreadyLoaded);
+    }
+    let library = Library::new(path).map_err(LoadError::Library)?;
+    let core = unsafe {
+        Core {
+            core: CoreFunctions::load(&library)?,
+            draw: DrawFunctions::load(&library)?,
+            logging: LoggingFunctions::load(&library)?,
+            moc: MocFunctions::load(&library)?,
+            model: ModelFunctions::load(&library)?,
+            _library: library,
+        }
+    };
+    CORE.store(Box::into_raw(Box::new(core)), Ordering::Release);
+    Ok(())
+}
+
+/// Returns true if the core library has been loaded.
+pub fn is_core_loaded() -> bool {
+    !CORE.load(Ordering::Acquire).is_null()
+}
+
+/// Returns the loaded core.
+///
+/// # Panics
+///
+/// Panics if the core has not been loaded yet.
+pub fn core() -> &'static Core {
+    let core = CORE.load(Ordering::Acquire);
+    if core.is_null() {
+        panic!("the cubism core has not been loaded, call load_core first");
+    }
+    // the core is never freed once stored
+    unsafe { &*core }
+}
+
+#[test]
+fn load_failures() {
+    match load_core("/nonexistent/libLive2DCubismCore.so") {
+        Err(LoadError::Library(_)) => (),
+        other => panic!("unexpected result {:?}", other),
+    }
+    #[cfg(target_os = "linux")]
+    match load_core("libc.so.6") {
+        Err(LoadError::MissingSymbol("csmGetVersion")) => (),
+        other => panic!("unexpected result {:?}", other),
+    }
+    assert!(!is_core_loaded());
+
+    // pretend a core has been loaded, the pointer is never dereferenced
+    CORE.store(ptr::NonNull::dangling().as_ptr(), Ordering::Release);
+    let result = load_core("/nonexistent/libLive2DCubismCore.so");
+    CORE.store(ptr::null_mut(), Ordering::Release);
+    match result {
+        Err(LoadError::AlreadyLoaded) => (),
+        other => panic!("unexpected result {:?}", other),
+    }
+}
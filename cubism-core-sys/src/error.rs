@@ -0,0 +1,36 @@
+//! The error returned by [load_core](../dynamic/fn.load_core.html).
+//!
+//! Defined regardless of the `dynamic` feature so that code matching on it doesn't depend on the feature.
+use std::{error, fmt, io};
+
+/// The error returned when loading the core library fails.
+#[derive(Debug)]
+pub enum LoadError {
+    /// The library could not be opened.
+    Library(io::Error),
+    /// The library does not export the named symbol, most likely because it is too old.
+    MissingSymbol(&'static str),
+    /// A core library has already been loaded.
+    AlreadyLoaded,
+}
+
+impl error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            LoadError::Library(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LoadError::Library(ref err) => write!(fmt, "failed to load the cubism core: {}", err),
+            LoadError::MissingSymbol(name) => {
+                write!(fmt, "the cubism core is missing the symbol {}", name)
+            }
+            LoadError::AlreadyLoaded => fmt.write_str("the cubism core has already been loaded"),
+        }
+    }
+}
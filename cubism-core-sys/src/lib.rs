@@ -5,13 +5,29 @@
 #![allow(non_upper_case_globals)]
 
 extern crate libc;
+#[cfg(feature = "dynamic")]
+extern crate libloading;
+
+#[cfg(all(feature = "dynamic", feature = "mock-core"))]
+compile_error!("the `dynamic` and `mock-core` features are mutually exclusive");
+
+#[macro_use]
+mod macros;
 
 pub mod draw;
+#[cfg(feature = "dynamic")]
+pub mod dynamic;
+pub mod error;
 pub mod logging;
 pub mod moc;
+#[cfg(feature = "mock-core")]
+pub mod mock;
 pub mod model;
 
 pub use draw::*;
+#[cfg(feature = "dynamic")]
+pub use dynamic::*;
+pub use error::*;
 pub use logging::*;
 pub use moc::*;
 pub use model::*;
@@ -24,6 +40,16 @@ pub struct csmVector2 {
     pub y: libc::c_float,
 }
 
-extern "C" {
-    pub fn csmGetVersion() -> csmVersion;
+#[repr(C)]
+pub struct csmVector4 {
+    pub x: libc::c_float,
+    pub y: libc::c_float,
+    pub z: libc::c_float,
+    pub w: libc::c_float,
+}
+
+csm_functions! {
+    core: CoreFunctions {
+        pub fn csmGetVersion() -> csmVersion;
+    }
 }
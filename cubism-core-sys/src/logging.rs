@@ -2,7 +2,9 @@ use libc::c_char;
 
 pub type csmLogFunction = Option<unsafe extern "C" fn(message: *const c_char)>;
 
-extern "C" {
-    pub fn csmGetLogFunction() -> csmLogFunction;
-    pub fn csmSetLogFunction(handler: csmLogFunction);
+csm_functions! {
+    logging: LoggingFunctions {
+        pub fn csmGetLogFunction() -> csmLogFunction;
+        pub fn csmSetLogFunction(handler: csmLogFunction);
+    }
 }
@@ -0,0 +1,48 @@
+/// Declares the functions of the core.
+///
+/// When linking statically this expands to a plain `extern "C"` block. With the `dynamic` feature
+/// it instead expands to a table of function pointers, which is filled when the library is loaded,
+/// and to wrapper functions of the same signature that call through the loaded table.
+macro_rules! csm_functions {
+    ($field:ident: $table:ident {
+        $(pub fn $name:ident($($arg:ident: $ty:ty),* $(,)*) $(-> $ret:ty)*;)*
+    }) => {
+        #[cfg(not(feature = "dynamic"))]
+        extern "C" {
+            $(pub fn $name($($arg: $ty),*) $(-> $ret)*;)*
+        }
+
+        /// The functions of this module resolved from a dynamically loaded core.
+        #[cfg(feature = "dynamic")]
+        pub struct $table {
+            $(pub $name: unsafe extern "C" fn($($ty),*) $(-> $ret)*,)*
+        }
+
+        #[cfg(feature = "dynamic")]
+        impl $table {
+            pub(crate) unsafe fn load(
+                library: &::libloading::Library,
+            ) -> Result<Self, ::LoadError> {
+                Ok($table {
+                    $($name: *library
+                        .get(concat!(stringify!($name), "\0").as_bytes())
+                        .map_err(|_| ::LoadError::MissingSymbol(stringify!($name)))?,)*
+                })
+            }
+        }
+
+        $(
+            /// Calls the function of the same name of the loaded core.
+            ///
+            /// # Safety
+            ///
+            /// The same requirements as for the core function apply,
+            /// panics if the core hasn't been loaded yet.
+            #[cfg(feature = "dynamic")]
+            #[inline]
+            pub unsafe fn $name($($arg: $ty),*) $(-> $ret)* {
+                (::dynamic::core().$field.$name)($($arg),*)
+            }
+        )*
+    };
+}
@@ -1,14 +1,28 @@
-use libc::{c_uint, c_void};
+use libc::{c_int, c_uint, c_void};
 
 pub const csmAlignofMoc: usize = 64;
 
+pub const csmMocVersion_Unknown: csmMocVersion = 0;
+pub const csmMocVersion_30: csmMocVersion = 1;
+pub const csmMocVersion_33: csmMocVersion = 2;
+pub const csmMocVersion_40: csmMocVersion = 3;
+pub const csmMocVersion_42: csmMocVersion = 4;
+pub const csmMocVersion_50: csmMocVersion = 5;
+
+pub type csmMocVersion = c_uint;
+
 #[repr(C, align(64))]
 pub struct csmMoc {
     _unused: [u64; 0],
 }
 
-extern "C" {
-    pub fn csmReviveMocInPlace(aligned_address: *mut c_void, size: c_uint) -> *mut csmMoc;
+csm_functions! {
+    moc: MocFunctions {
+        pub fn csmGetLatestMocVersion() -> csmMocVersion;
+        pub fn csmGetMocVersion(address: *const c_void, size: c_uint) -> csmMocVersion;
+        pub fn csmHasMocConsistency(address: *mut c_void, size: c_uint) -> c_int;
+        pub fn csmReviveMocInPlace(aligned_address: *mut c_void, size: c_uint) -> *mut csmMoc;
+    }
 }
 
 #[test]
@@ -0,0 +1,1100 @@
+//! A pure rust stand-in for the Live2DCubismCore library.
+//!
+//! Enabled by the `mock-core` feature, this module exports every symbol the
+//! bindings declare so that the crate links without the proprietary sdk.
+//! Mocs are not real .moc3 files but a small synthetic format produced by
+//! [MockMoc::to_bytes](struct.MockMoc.html#method.to_bytes).
+//!
+//! The simulated core is intentionally simple and deterministic:
+//! * a drawable's opacity is its base opacity multiplied by the opacity of the part it belongs to,
+//! * a drawable bound to a parameter has its vertices translated along the x axis by that parameter's value,
+//! * the render order is the rank of a drawable's draw order,
+//! * drawables with an opacity of zero are invisible.
+#![allow(missing_docs)]
+
+use libc::{c_char, c_float, c_int, c_uint, c_ushort, c_void};
+
+use std::ffi::CString;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::{mem, ptr, slice};
+
+use csmVector2;
+use csmVector4;
+use csmVersion;
+use draw::{
+    csmBlendColorDidChange, csmDrawOrderDidChange, csmFlags, csmIsVisible, csmOpacityDidChange,
+    csmRenderOrderDidChange, csmVertexPositionsDidChange, csmVisibilityDidChange,
+};
+use logging::csmLogFunction;
+use moc::{csmAlignofMoc, csmMoc, csmMocVersion, csmMocVersion_50, csmMocVersion_Unknown};
+use model::{csmAlignofModel, csmModel, csmParameterType, csmParameterType_Normal};
+
+/// The core version reported by the mock.
+pub const MOCK_CORE_VERSION: csmVersion = 0x0500_0000;
+
+const MAGIC: &[u8; 4] = b"MOCK";
+
+/// A parameter of a [MockMoc](struct.MockMoc.html).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MockParameter {
+    pub id: String,
+    pub minimum: f32,
+    pub maximum: f32,
+    pub default: f32,
+    pub ty: csmParameterType,
+    pub key_values: Vec<f32>,
+    pub repeat: bool,
+}
+
+impl MockParameter {
+    /// Creates a normal, non repeating parameter with keys at its minimum, default and maximum.
+    pub fn new(id: &str, minimum: f32, maximum: f32, default: f32) -> Self {
+        let mut key_values = vec![minimum, default, maximum];
+        key_values.dedup();
+        MockParameter {
+            id: id.to_owned(),
+            minimum,
+            maximum,
+            default,
+            ty: csmParameterType_Normal,
+            key_values,
+            repeat: false,
+        }
+    }
+}
+
+/// A part of a [MockMoc](struct.MockMoc.html).
+#[derive(Clone, Debug, PartialEq)]
+pub struct MockPart {
+    pub id: String,
+    /// The index of the parent part, or -1.
+    pub parent: i32,
+}
+
+impl MockPart {
+    pub fn new(id: &str) -> Self {
+        MockPart {
+            id: id.to_owned(),
+            parent: -1,
+        }
+    }
+}
+
+/// A drawable of a [MockMoc](struct.MockMoc.html).
+#[derive(Clone, Debug, PartialEq)]
+pub struct MockDrawable {
+    pub id: String,
+    pub constant_flags: csmFlags,
+    pub texture_index: i32,
+    pub draw_order: i32,
+    pub opacity: f32,
+    /// The index of the part this drawable belongs to, or -1.
+    pub part: i32,
+    /// The index of the parameter that translates this drawable, or -1.
+    pub parameter: i32,
+    pub masks: Vec<i32>,
+    pub positions: Vec<[f32; 2]>,
+    pub uvs: Vec<[f32; 2]>,
+    pub indices: Vec<u16>,
+    pub multiply_color: [f32; 4],
+    pub screen_color: [f32; 4],
+}
+
+impl Default for MockDrawable {
+    fn default() -> Self {
+        MockDrawable {
+            id: String::new(),
+            constant_flags: 0,
+            texture_index: 0,
+            draw_order: 0,
+            opacity: 1.0,
+            part: -1,
+            parameter: -1,
+            masks: Vec::new(),
+            positions: Vec::new(),
+            uvs: Vec::new(),
+            indices: Vec::new(),
+            multiply_color: [1.0, 1.0, 1.0, 1.0],
+            screen_color: [0.0, 0.0, 0.0, 1.0],
+        }
+    }
+}
+
+impl MockDrawable {
+    /// Creates an axis aligned quad spanning from `min` to `max` made of two triangles.
+    pub fn quad(id: &str, min: [f32; 2], max: [f32; 2]) -> Self {
+        MockDrawable {
+            id: id.to_owned(),
+            positions: vec![min, [max[0], min[1]], max, [min[0], max[1]]],
+            uvs: vec![[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]],
+            indices: vec![0, 1, 2, 2, 3, 0],
+            ..Default::default()
+        }
+    }
+}
+
+/// A synthetic model description that can be serialized into a moc the mock core understands.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MockMoc {
+    /// The moc version reported by `csmGetMocVersion`.
+    pub version: csmMocVersion,
+    pub canvas_size: [f32; 2],
+    pub canvas_origin: [f32; 2],
+    pub pixels_per_unit: f32,
+    pub parameters: Vec<MockParameter>,
+    pub parts: Vec<MockPart>,
+    pub drawables: Vec<MockDrawable>,
+}
+
+impl Default for MockMoc {
+    fn default() -> Self {
+        MockMoc {
+            version: csmMocVersion_50,
+            canvas_size: [100.0, 100.0],
+            canvas_origin: [50.0, 50.0],
+            pixels_per_unit: 100.0,
+            parameters: Vec::new(),
+            parts: Vec::new(),
+            drawables: Vec::new(),
+        }
+    }
+}
+
+impl MockMoc {
+    /// A small model with two parameters, two parts and three drawables.
+    ///
+    /// `PartArm` is a child of `PartBody`.
+    /// `ArtMesh0` belongs to `PartBody`, `ArtMesh1` belongs to `PartArm` and is moved by `ParamArmX`,
+    /// `ArtMesh2` belongs to no part, is drawn first and masked by `ArtMesh0`.
+    pub fn sample() -> Self {
+        MockMoc {
+            parameters: vec![
+                MockParameter::new("ParamAngleX", -30.0, 30.0, 0.0),
+                MockParameter::new("ParamArmX", -1.0, 1.0, 0.5),
+            ],
+            parts: vec![
+                MockPart::new("PartBody"),
+                MockPart {
+                    parent: 0,
+                    ..MockPart::new("PartArm")
+                },
+            ],
+            drawables: vec![
+                MockDrawable {
+                    draw_order: 500,
+                    part: 0,
+                    ..MockDrawable::quad("ArtMesh0", [-0.5, -0.5], [0.5, 0.5])
+                },
+                MockDrawable {
+                    texture_index: 1,
+                    draw_order: 600,
+                    part: 1,
+                    parameter: 1,
+                    ..MockDrawable::quad("ArtMesh1", [0.0, 0.0], [0.25, 0.25])
+                },
+                MockDrawable {
+                    draw_order: 400,
+                    masks: vec![0],
+                    ..MockDrawable::quad("ArtMesh2", [-1.0, -1.0], [0.0, 0.0])
+                },
+            ],
+            ..Default::default()
+        }
+    }
+
+    /// Serializes this description into moc data.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut w = Writer(Vec::new());
+        w.0.extend_from_slice(MAGIC);
+        // total length, patched below
+        w.u32(0);
+        w.u32(self.version);
+        w.f32s(&self.canvas_size);
+        w.f32s(&self.canvas_origin);
+        w.f32(self.pixels_per_unit);
+        w.u32(self.parameters.len() as u32);
+        for param in &self.parameters {
+            w.str(&param.id);
+            w.f32(param.minimum);
+            w.f32(param.maximum);
+            w.f32(param.default);
+            w.i32(param.ty);
+            w.u32(param.key_values.len() as u32);
+            w.f32s(&param.key_values);
+            w.0.push(param.repeat as u8);
+        }
+        w.u32(self.parts.len() as u32);
+        for part in &self.parts {
+            w.str(&part.id);
+            w.i32(part.parent);
+        }
+        w.u32(self.drawables.len() as u32);
+        for drawable in &self.drawables {
+            w.str(&drawable.id);
+            w.0.push(drawable.constant_flags);
+            w.i32(drawable.texture_index);
+            w.i32(drawable.draw_order);
+            w.f32(drawable.opacity);
+            w.i32(drawable.part);
+            w.i32(drawable.parameter);
+            w.u32(drawable.masks.len() as u32);
+            for &mask in &drawable.masks {
+                w.i32(mask);
+            }
+            w.u32(drawable.positions.len() as u32);
+            for i in 0..drawable.positions.len() {
+                w.f32s(&drawable.positions[i]);
+                w.f32s(drawable.uvs.get(i).unwrap_or(&[0.0, 0.0]));
+            }
+            w.u32(drawable.indices.len() as u32);
+            for &index in &drawable.indices {
+                w.0.extend_from_slice(&index.to_le_bytes());
+            }
+            w.f32s(&drawable.multiply_color);
+            w.f32s(&drawable.screen_color);
+        }
+        let len = w.0.len() as u32;
+        w.0[4..8].copy_from_slice(&len.to_le_bytes());
+        w.0
+    }
+}
+
+struct Writer(Vec<u8>);
+
+impl Writer {
+    fn u32(&mut self, v: u32) {
+        self.0.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn i32(&mut self, v: i32) {
+        self.0.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn f32(&mut self, v: f32) {
+        self.0.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn f32s(&mut self, v: &[f32]) {
+        for &v in v {
+            self.f32(v);
+        }
+    }
+
+    fn str(&mut self, s: &str) {
+        self.u32(s.len() as u32);
+        self.0.extend_from_slice(s.as_bytes());
+        self.0.push(0);
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, n: usize) -> Option<&'a [u8]> {
+        let end = self.pos.checked_add(n)?;
+        let out = self.data.get(self.pos..end)?;
+        self.pos = end;
+        Some(out)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.bytes(1).map(|b| b[0])
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        let mut buf = [0; 4];
+        buf.copy_from_slice(self.bytes(4)?);
+        Some(u32::from_le_bytes(buf))
+    }
+
+    fn i32(&mut self) -> Option<i32> {
+        self.u32().map(|v| v as i32)
+    }
+
+    fn f32(&mut self) -> Option<f32> {
+        self.u32().map(f32::from_bits)
+    }
+
+    fn f32x2(&mut self) -> Option<[f32; 2]> {
+        Some([self.f32()?, self.f32()?])
+    }
+
+    fn f32x4(&mut self) -> Option<[f32; 4]> {
+        Some([self.f32()?, self.f32()?, self.f32()?, self.f32()?])
+    }
+
+    fn len(&mut self) -> Option<usize> {
+        let len = self.u32()? as usize;
+        // every element takes at least one byte, this rejects absurd lengths early
+        if len > self.data.len() - self.pos {
+            None
+        } else {
+            Some(len)
+        }
+    }
+
+    /// Reads a string, returning it and its offset into the data.
+    fn str(&mut self) -> Option<(String, usize)> {
+        let len = self.len()?;
+        let offset = self.pos;
+        let bytes = self.bytes(len)?;
+        if self.u8()? != 0 || bytes.contains(&0) {
+            return None;
+        }
+        Some((String::from_utf8(bytes.to_vec()).ok()?, offset))
+    }
+}
+
+/// A parsed moc, the id offsets point into the moc memory.
+struct Parsed {
+    desc: MockMoc,
+    parameter_ids: Vec<usize>,
+    part_ids: Vec<usize>,
+    drawable_ids: Vec<usize>,
+}
+
+fn parse(data: &[u8]) -> Option<Parsed> {
+    let mut r = Reader { data, pos: 0 };
+    if r.bytes(4)? != MAGIC {
+        return None;
+    }
+    let len = r.u32()? as usize;
+    if len > data.len() || len < r.pos {
+        return None;
+    }
+    r.data = &data[..len];
+    let mut desc = MockMoc {
+        version: r.u32()?,
+        canvas_size: r.f32x2()?,
+        canvas_origin: r.f32x2()?,
+        pixels_per_unit: r.f32()?,
+        ..Default::default()
+    };
+    let (mut parameter_ids, mut part_ids, mut drawable_ids) = (Vec::new(), Vec::new(), Vec::new());
+    for _ in 0..r.len()? {
+        let (id, offset) = r.str()?;
+        parameter_ids.push(offset);
+        let (minimum, maximum, default, ty) = (r.f32()?, r.f32()?, r.f32()?, r.i32()?);
+        let mut key_values = Vec::new();
+        for _ in 0..r.len()? {
+            key_values.push(r.f32()?);
+        }
+        desc.parameters.push(MockParameter {
+            id,
+            minimum,
+            maximum,
+            default,
+            ty,
+            key_values,
+            repeat: r.u8()? != 0,
+        });
+    }
+    for _ in 0..r.len()? {
+        let (id, offset) = r.str()?;
+        part_ids.push(offset);
+        desc.parts.push(MockPart {
+            id,
+            parent: r.i32()?,
+        });
+    }
+    for _ in 0..r.len()? {
+        let (id, offset) = r.str()?;
+        drawable_ids.push(offset);
+        let mut drawable = MockDrawable {
+            id,
+            constant_flags: r.u8()?,
+            texture_index: r.i32()?,
+            draw_order: r.i32()?,
+            opacity: r.f32()?,
+            part: r.i32()?,
+            parameter: r.i32()?,
+            ..Default::default()
+        };
+        for _ in 0..r.len()? {
+            drawable.masks.push(r.i32()?);
+        }
+        for _ in 0..r.len()? {
+            drawable.positions.push(r.f32x2()?);
+            drawable.uvs.push(r.f32x2()?);
+        }
+        for _ in 0..r.len()? {
+            let mut buf = [0; 2];
+            buf.copy_from_slice(r.bytes(2)?);
+            drawable.indices.push(u16::from_le_bytes(buf));
+        }
+        drawable.multiply_color = r.f32x4()?;
+        drawable.screen_color = r.f32x4()?;
+        desc.drawables.push(drawable);
+    }
+    if r.pos != len || desc.version > csmMocVersion_50 || !is_consistent(&desc) {
+        return None;
+    }
+    Some(Parsed {
+        desc,
+        parameter_ids,
+        part_ids,
+        drawable_ids,
+    })
+}
+
+fn is_consistent(desc: &MockMoc) -> bool {
+    let in_range = |idx: i32, len: usize| idx == -1 || (idx >= 0 && (idx as usize) < len);
+    let parts = desc.parts.len();
+    // a parent chain longer than the number of parts has to contain a cycle
+    let is_acyclic = |start: usize| {
+        let mut part = start as i32;
+        (0..=parts).any(|_| {
+            part = desc.parts[part as usize].parent;
+            part == -1
+        })
+    };
+    desc.parameters.iter().all(|p| p.minimum <= p.maximum)
+        && desc.parts.iter().all(|p| in_range(p.parent, parts))
+        && (0..parts).all(is_acyclic)
+        && desc.drawables.iter().all(|d| {
+            in_range(d.part, parts)
+                && in_range(d.parameter, desc.parameters.len())
+                && d.masks
+                    .iter()
+                    .all(|&m| m >= 0 && (m as usize) < desc.drawables.len())
+                && d.indices.len() % 3 == 0
+                && d.indices.iter().all(|&i| (i as usize) < d.positions.len())
+        })
+}
+
+/// Parses a revived moc back from its memory.
+unsafe fn parse_moc(moc: *const csmMoc) -> Parsed {
+    let len = ptr::read((moc as *const u8).add(4) as *const [u8; 4]);
+    let data = slice::from_raw_parts(moc as *const u8, u32::from_le_bytes(len) as usize);
+    // the moc has been validated by csmReviveMocInPlace
+    parse(data).expect("mock moc has been modified after being revived")
+}
+
+/// The state of a model, stored at the start of the model memory.
+///
+/// All pointers point into the model memory itself except for the ids which point into the moc.
+#[repr(C)]
+struct ModelHeader {
+    canvas_size: [f32; 2],
+    canvas_origin: [f32; 2],
+    pixels_per_unit: f32,
+    parameter_count: usize,
+    part_count: usize,
+    drawable_count: usize,
+
+    parameter_ids: *mut *const c_char,
+    parameter_minimums: *mut f32,
+    parameter_maximums: *mut f32,
+    parameter_defaults: *mut f32,
+    parameter_values: *mut f32,
+    parameter_types: *mut csmParameterType,
+    parameter_key_counts: *mut c_int,
+    parameter_key_values: *mut *const c_float,
+    parameter_repeats: *mut c_int,
+
+    part_ids: *mut *const c_char,
+    part_opacities: *mut f32,
+    part_parents: *mut c_int,
+
+    drawable_ids: *mut *const c_char,
+    drawable_constant_flags: *mut csmFlags,
+    drawable_dynamic_flags: *mut csmFlags,
+    drawable_texture_indices: *mut c_int,
+    drawable_draw_orders: *mut c_int,
+    drawable_render_orders: *mut c_int,
+    drawable_opacities: *mut f32,
+    drawable_base_opacities: *mut f32,
+    drawable_parts: *mut c_int,
+    drawable_parameters: *mut c_int,
+    drawable_mask_counts: *mut c_int,
+    drawable_masks: *mut *const c_int,
+    drawable_vertex_counts: *mut c_int,
+    drawable_vertex_positions: *mut *const csmVector2,
+    drawable_base_positions: *mut *const csmVector2,
+    drawable_vertex_uvs: *mut *const csmVector2,
+    drawable_index_counts: *mut c_int,
+    drawable_indices: *mut *const c_ushort,
+    drawable_multiply_colors: *mut csmVector4,
+    drawable_screen_colors: *mut csmVector4,
+}
+
+/// Hands out properly aligned regions of the model memory.
+///
+/// With a null base this only computes the required size.
+struct Arena {
+    base: *mut u8,
+    size: usize,
+}
+
+impl Arena {
+    fn alloc<T>(&mut self, n: usize) -> *mut T {
+        let align = mem::align_of::<T>();
+        self.size = (self.size + align - 1) & !(align - 1);
+        let out = self.base.wrapping_add(self.size) as *mut T;
+        self.size += n * mem::size_of::<T>();
+        out
+    }
+}
+
+/// Lays out a model for `parsed` in `base`, or only computes its size if `base` is null.
+unsafe fn layout_model(moc: *const csmMoc, parsed: &Parsed, base: *mut u8) -> usize {
+    let desc = &parsed.desc;
+    let (param_count, part_count, drawable_count) = (
+        desc.parameters.len(),
+        desc.parts.len(),
+        desc.drawables.len(),
+    );
+    let vertex_total: usize = desc.drawables.iter().map(|d| d.positions.len()).sum();
+    let index_total: usize = desc.drawables.iter().map(|d| d.indices.len()).sum();
+    let mask_total: usize = desc.drawables.iter().map(|d| d.masks.len()).sum();
+    let key_total: usize = desc.parameters.iter().map(|p| p.key_values.len()).sum();
+
+    let mut arena = Arena { base, size: 0 };
+    let header = arena.alloc::<ModelHeader>(1);
+    let h = ModelHeader {
+        canvas_size: desc.canvas_size,
+        canvas_origin: desc.canvas_origin,
+        pixels_per_unit: desc.pixels_per_unit,
+        parameter_count: param_count,
+        part_count,
+        drawable_count,
+        parameter_ids: arena.alloc(param_count),
+        parameter_minimums: arena.alloc(param_count),
+        parameter_maximums: arena.alloc(param_count),
+        parameter_defaults: arena.alloc(param_count),
+        parameter_values: arena.alloc(param_count),
+        parameter_types: arena.alloc(param_count),
+        parameter_key_counts: arena.alloc(param_count),
+        parameter_key_values: arena.alloc(param_count),
+        parameter_repeats: arena.alloc(param_count),
+        part_ids: arena.alloc(part_count),
+        part_opacities: arena.alloc(part_count),
+        part_parents: arena.alloc(part_count),
+        drawable_ids: arena.alloc(drawable_count),
+        drawable_constant_flags: arena.alloc(drawable_count),
+        drawable_dynamic_flags: arena.alloc(drawable_count),
+        drawable_texture_indices: arena.alloc(drawable_count),
+        drawable_draw_orders: arena.alloc(drawable_count),
+        drawable_render_orders: arena.alloc(drawable_count),
+        drawable_opacities: arena.alloc(drawable_count),
+        drawable_base_opacities: arena.alloc(drawable_count),
+        drawable_parts: arena.alloc(drawable_count),
+        drawable_parameters: arena.alloc(drawable_count),
+        drawable_mask_counts: arena.alloc(drawable_count),
+        drawable_masks: arena.alloc(drawable_count),
+        drawable_vertex_counts: arena.alloc(drawable_count),
+        drawable_vertex_positions: arena.alloc(drawable_count),
+        drawable_base_positions: arena.alloc(drawable_count),
+        drawable_vertex_uvs: arena.alloc(drawable_count),
+        drawable_index_counts: arena.alloc(drawable_count),
+        drawable_indices: arena.alloc(drawable_count),
+        drawable_multiply_colors: arena.alloc(drawable_count),
+        drawable_screen_colors: arena.alloc(drawable_count),
+    };
+    let mut key_values = arena.alloc::<c_float>(key_total);
+    let mut masks = arena.alloc::<c_int>(mask_total);
+    let mut positions = arena.alloc::<csmVector2>(vertex_total);
+    let mut base_positions = arena.alloc::<csmVector2>(vertex_total);
+    let mut uvs = arena.alloc::<csmVector2>(vertex_total);
+    let mut indices = arena.alloc::<c_ushort>(index_total);
+    if base.is_null() {
+        return arena.size;
+    }
+
+    let moc = moc as *const u8;
+    for (i, param) in desc.parameters.iter().enumerate() {
+        *h.parameter_ids.add(i) = moc.add(parsed.parameter_ids[i]) as *const c_char;
+        *h.parameter_minimums.add(i) = param.minimum;
+        *h.parameter_maximums.add(i) = param.maximum;
+        *h.parameter_defaults.add(i) = param.default;
+        *h.parameter_values.add(i) = param.default;
+        *h.parameter_types.add(i) = param.ty;
+        *h.parameter_repeats.add(i) = param.repeat as c_int;
+
+        let key_count = param.key_values.len();
+        *h.parameter_key_counts.add(i) = key_count as c_int;
+        *h.parameter_key_values.add(i) = key_values;
+        ptr::copy_nonoverlapping(param.key_values.as_ptr(), key_values, key_count);
+        key_values = key_values.add(key_count);
+    }
+    for (i, part) in desc.parts.iter().enumerate() {
+        *h.part_ids.add(i) = moc.add(parsed.part_ids[i]) as *const c_char;
+        *h.part_opacities.add(i) = 1.0;
+        *h.part_parents.add(i) = part.parent;
+    }
+    for (i, d) in desc.drawables.iter().enumerate() {
+        *h.drawable_ids.add(i) = moc.add(parsed.drawable_ids[i]) as *const c_char;
+        *h.drawable_constant_flags.add(i) = d.constant_flags;
+        // everything counts as changed until the first reset
+        let visible = if d.opacity > 0.0 { csmIsVisible } else { 0 };
+        *h.drawable_dynamic_flags.add(i) = visible
+            | csmVisibilityDidChange
+            | csmOpacityDidChange
+            | csmDrawOrderDidChange
+            | csmRenderOrderDidChange
+            | csmVertexPositionsDidChange
+            | csmBlendColorDidChange;
+        *h.drawable_texture_indices.add(i) = d.texture_index;
+        *h.drawable_draw_orders.add(i) = d.draw_order;
+        *h.drawable_render_orders.add(i) = 0;
+        *h.drawable_opacities.add(i) = d.opacity;
+        *h.drawable_base_opacities.add(i) = d.opacity;
+        *h.drawable_parts.add(i) = d.part;
+        *h.drawable_parameters.add(i) = d.parameter;
+
+        *h.drawable_mask_counts.add(i) = d.masks.len() as c_int;
+        *h.drawable_masks.add(i) = masks;
+        ptr::copy_nonoverlapping(d.masks.as_ptr(), masks, d.masks.len());
+        masks = masks.add(d.masks.len());
+
+        let vertex_count = d.positions.len();
+        *h.drawable_vertex_counts.add(i) = vertex_count as c_int;
+        *h.drawable_vertex_positions.add(i) = positions;
+        *h.drawable_base_positions.add(i) = base_positions;
+        *h.drawable_vertex_uvs.add(i) = uvs;
+        for v in 0..vertex_count {
+            let (pos, uv) = (d.positions[v], d.uvs[v]);
+            ptr::write(
+                positions.add(v),
+                csmVector2 {
+                    x: pos[0],
+                    y: pos[1],
+                },
+            );
+            ptr::write(
+                base_positions.add(v),
+                csmVector2 {
+                    x: pos[0],
+                    y: pos[1],
+                },
+            );
+            ptr::write(uvs.add(v), csmVector2 { x: uv[0], y: uv[1] });
+        }
+        positions = positions.add(vertex_count);
+        base_positions = base_positions.add(vertex_count);
+        uvs = uvs.add(vertex_count);
+
+        let (mul, scr) = (d.multiply_color, d.screen_color);
+        ptr::write(
+            h.drawable_multiply_colors.add(i),
+            csmVector4 {
+                x: mul[0],
+                y: mul[1],
+                z: mul[2],
+                w: mul[3],
+            },
+        );
+        ptr::write(
+            h.drawable_screen_colors.add(i),
+            csmVector4 {
+                x: scr[0],
+                y: scr[1],
+                z: scr[2],
+                w: scr[3],
+            },
+        );
+
+        *h.drawable_index_counts.add(i) = d.indices.len() as c_int;
+        *h.drawable_indices.add(i) = indices;
+        ptr::copy_nonoverlapping(d.indices.as_ptr(), indices, d.indices.len());
+        indices = indices.add(d.indices.len());
+    }
+    ptr::write(header, h);
+    update_render_orders(&*header);
+    arena.size
+}
+
+#[inline]
+unsafe fn header<'a>(model: *const csmModel) -> &'a ModelHeader {
+    &*(model as *const ModelHeader)
+}
+
+unsafe fn update_render_orders(h: &ModelHeader) -> bool {
+    let draw_orders = slice::from_raw_parts(h.drawable_draw_orders, h.drawable_count);
+    let render_orders = slice::from_raw_parts_mut(h.drawable_render_orders, h.drawable_count);
+    let mut sorted: Vec<usize> = (0..h.drawable_count).collect();
+    sorted.sort_by_key(|&i| draw_orders[i]);
+    let mut changed = false;
+    for (rank, &idx) in sorted.iter().enumerate() {
+        changed |= render_orders[idx] != rank as c_int;
+        render_orders[idx] = rank as c_int;
+    }
+    changed
+}
+
+static LOG_FUNCTION: AtomicUsize = AtomicUsize::new(0);
+
+fn log(message: &str) {
+    let handler = LOG_FUNCTION.load(Ordering::SeqCst);
+    if handler != 0 {
+        let handler =
+            unsafe { mem::transmute::<usize, unsafe extern "C" fn(*const c_char)>(handler) };
+        let message = CString::new(message).unwrap();
+        unsafe { handler(message.as_ptr()) };
+    }
+}
+
+#[no_mangle]
+unsafe extern "C" fn csmGetVersion() -> csmVersion {
+    MOCK_CORE_VERSION
+}
+
+#[no_mangle]
+unsafe extern "C" fn csmGetLogFunction() -> csmLogFunction {
+    match LOG_FUNCTION.load(Ordering::SeqCst) {
+        0 => None,
+        handler => Some(mem::transmute::<usize, unsafe extern "C" fn(*const c_char)>(handler)),
+    }
+}
+
+#[no_mangle]
+unsafe extern "C" fn csmSetLogFunction(handler: csmLogFunction) {
+    LOG_FUNCTION.store(handler.map_or(0, |h| h as usize), Ordering::SeqCst);
+}
+
+#[no_mangle]
+unsafe extern "C" fn csmGetLatestMocVersion() -> csmMocVersion {
+    csmMocVersion_50
+}
+
+#[no_mangle]
+unsafe extern "C" fn csmGetMocVersion(address: *const c_void, size: c_uint) -> csmMocVersion {
+    if address.is_null() || size < 12 {
+        return csmMocVersion_Unknown;
+    }
+    let data = slice::from_raw_parts(address as *const u8, size as usize);
+    let mut r = Reader { data, pos: 0 };
+    match (r.bytes(4), r.u32(), r.u32()) {
+        (Some(magic), Some(_), Some(version)) if magic == MAGIC => version,
+        _ => csmMocVersion_Unknown,
+    }
+}
+
+#[no_mangle]
+unsafe extern "C" fn csmHasMocConsistency(address: *mut c_void, size: c_uint) -> c_int {
+    if address.is_null() || address as usize & (csmAlignofMoc - 1) != 0 {
+        log("[CSM] [E]HasMocConsistency: Address is not aligned.");
+        return 0;
+    }
+    let data = slice::from_raw_parts(address as *const u8, size as usize);
+    if parse(data).is_none() {
+        log("[CSM] [E]HasMocConsistency: Moc is inconsistent.");
+        return 0;
+    }
+    1
+}
+
+#[no_mangle]
+unsafe extern "C" fn csmReviveMocInPlace(
+    aligned_address: *mut c_void,
+    size: c_uint,
+) -> *mut csmMoc {
+    if aligned_address.is_null() || aligned_address as usize & (csmAlignofMoc - 1) != 0 {
+        log("[CSM] [E]ReviveMocInPlace: Address is not aligned.");
+        return ptr::null_mut();
+    }
+    let data = slice::from_raw_parts(aligned_address as *const u8, size as usize);
+    if parse(data).is_none() {
+        log("[CSM] [E]ReviveMocInPlace: Invalid moc data.");
+        return ptr::null_mut();
+    }
+    aligned_address as *mut csmMoc
+}
+
+#[no_mangle]
+unsafe extern "C" fn csmGetSizeofModel(moc: *const csmMoc) -> c_uint {
+    layout_model(moc, &parse_moc(moc), ptr::null_mut()) as c_uint
+}
+
+#[no_mangle]
+unsafe extern "C" fn csmInitializeModelInPlace(
+    moc: *const csmMoc,
+    aligned_address: *mut c_void,
+    size: c_uint,
+) -> *mut csmModel {
+    if aligned_address.is_null() || aligned_address as usize & (csmAlignofModel - 1) != 0 {
+        log("[CSM] [E]InitializeModelInPlace: Address is not aligned.");
+        return ptr::null_mut();
+    }
+    let parsed = parse_moc(moc);
+    if (size as usize) < layout_model(moc, &parsed, ptr::null_mut()) {
+        log("[CSM] [E]InitializeModelInPlace: Size is too small.");
+        return ptr::null_mut();
+    }
+    layout_model(moc, &parsed, aligned_address as *mut u8);
+    aligned_address as *mut csmModel
+}
+
+#[no_mangle]
+unsafe extern "C" fn csmUpdateModel(model: *mut csmModel) {
+    let h = header(model);
+    let values = slice::from_raw_parts(h.parameter_values, h.parameter_count);
+    let minimums = slice::from_raw_parts(h.parameter_minimums, h.parameter_count);
+    let maximums = slice::from_raw_parts(h.parameter_maximums, h.parameter_count);
+    let part_opacities = slice::from_raw_parts(h.part_opacities, h.part_count);
+    let part_parents = slice::from_raw_parts(h.part_parents, h.part_count);
+    for i in 0..h.drawable_count {
+        let mut flags = *h.drawable_dynamic_flags.add(i);
+
+        // part opacities cascade down the part hierarchy
+        let mut part = *h.drawable_parts.add(i);
+        let mut part_opacity = 1.0;
+        while part >= 0 {
+            part_opacity *= part_opacities[part as usize].clamp(0.0, 1.0);
+            part = part_parents[part as usize];
+        }
+        let opacity = *h.drawable_base_opacities.add(i) * part_opacity;
+        let old_opacity = *h.drawable_opacities.add(i);
+        if opacity != old_opacity {
+            flags |= csmOpacityDidChange;
+        }
+        if (opacity > 0.0) != (old_opacity > 0.0) {
+            flags |= csmVisibilityDidChange;
+        }
+        *h.drawable_opacities.add(i) = opacity;
+        if opacity > 0.0 {
+            flags |= csmIsVisible;
+        } else {
+            flags &= !csmIsVisible;
+        }
+
+        let param = *h.drawable_parameters.add(i);
+        let offset = if param >= 0 {
+            let p = param as usize;
+            values[p].clamp(minimums[p], maximums[p])
+        } else {
+            0.0
+        };
+        let count = *h.drawable_vertex_counts.add(i) as usize;
+        let base = *h.drawable_base_positions.add(i);
+        let positions = *h.drawable_vertex_positions.add(i) as *mut csmVector2;
+        for v in 0..count {
+            let (base, pos) = (&*base.add(v), &mut *positions.add(v));
+            let x = base.x + offset;
+            if pos.x != x || pos.y != base.y {
+                flags |= csmVertexPositionsDidChange;
+            }
+            pos.x = x;
+            pos.y = base.y;
+        }
+        *h.drawable_dynamic_flags.add(i) = flags;
+    }
+    if update_render_orders(h) {
+        for i in 0..h.drawable_count {
+            *h.drawable_dynamic_flags.add(i) |= csmRenderOrderDidChange;
+        }
+    }
+}
+
+#[no_mangle]
+unsafe extern "C" fn csmReadCanvasInfo(
+    model: *const csmModel,
+    outSizeInPixels: *mut csmVector2,
+    outOriginalInPixels: *mut csmVector2,
+    outPixelsPerUnit: *mut c_float,
+) {
+    let h = header(model);
+    ptr::write(
+        outSizeInPixels,
+        csmVector2 {
+            x: h.canvas_size[0],
+            y: h.canvas_size[1],
+        },
+    );
+    ptr::write(
+        outOriginalInPixels,
+        csmVector2 {
+            x: h.canvas_origin[0],
+            y: h.canvas_origin[1],
+        },
+    );
+    *outPixelsPerUnit = h.pixels_per_unit;
+}
+
+#[no_mangle]
+unsafe extern "C" fn csmGetParameterCount(model: *const csmModel) -> c_int {
+    header(model).parameter_count as c_int
+}
+
+#[no_mangle]
+unsafe extern "C" fn csmGetParameterIds(model: *const csmModel) -> *mut *const c_char {
+    header(model).parameter_ids
+}
+
+#[no_mangle]
+unsafe extern "C" fn csmGetParameterMinimumValues(model: *const csmModel) -> *const c_float {
+    header(model).parameter_minimums
+}
+
+#[no_mangle]
+unsafe extern "C" fn csmGetParameterMaximumValues(model: *const csmModel) -> *const c_float {
+    header(model).parameter_maximums
+}
+
+#[no_mangle]
+unsafe extern "C" fn csmGetParameterDefaultValues(model: *const csmModel) -> *const c_float {
+    header(model).parameter_defaults
+}
+
+#[no_mangle]
+unsafe extern "C" fn csmGetParameterValues(model: *mut csmModel) -> *mut c_float {
+    header(model).parameter_values
+}
+
+#[no_mangle]
+unsafe extern "C" fn csmGetParameterTypes(model: *const csmModel) -> *const csmParameterType {
+    header(model).parameter_types
+}
+
+#[no_mangle]
+unsafe extern "C" fn csmGetParameterKeyCounts(model: *const csmModel) -> *const c_int {
+    header(model).parameter_key_counts
+}
+
+#[no_mangle]
+unsafe extern "C" fn csmGetParameterKeyValues(model: *const csmModel) -> *mut *const c_float {
+    header(model).parameter_key_values
+}
+
+#[no_mangle]
+unsafe extern "C" fn csmGetParameterRepeats(model: *const csmModel) -> *const c_int {
+    header(model).parameter_repeats
+}
+
+#[no_mangle]
+unsafe extern "C" fn csmGetPartCount(model: *const csmModel) -> c_int {
+    header(model).part_count as c_int
+}
+
+#[no_mangle]
+unsafe extern "C" fn csmGetPartIds(model: *const csmModel) -> *mut *const c_char {
+    header(model).part_ids
+}
+
+#[no_mangle]
+unsafe extern "C" fn csmGetPartOpacities(model: *mut csmModel) -> *mut c_float {
+    header(model).part_opacities
+}
+
+#[no_mangle]
+unsafe extern "C" fn csmGetPartParentPartIndices(model: *const csmModel) -> *const c_int {
+    header(model).part_parents
+}
+
+#[no_mangle]
+unsafe extern "C" fn csmGetDrawableCount(model: *const csmModel) -> c_int {
+    header(model).drawable_count as c_int
+}
+
+#[no_mangle]
+unsafe extern "C" fn csmGetDrawableIds(model: *const csmModel) -> *mut *const c_char {
+    header(model).drawable_ids
+}
+
+#[no_mangle]
+unsafe extern "C" fn csmGetDrawableConstantFlags(model: *const csmModel) -> *const csmFlags {
+    header(model).drawable_constant_flags
+}
+
+#[no_mangle]
+unsafe extern "C" fn csmGetDrawableDynamicFlags(model: *const csmModel) -> *const csmFlags {
+    header(model).drawable_dynamic_flags
+}
+
+#[no_mangle]
+unsafe extern "C" fn csmGetDrawableTextureIndices(model: *const csmModel) -> *const c_int {
+    header(model).drawable_texture_indices
+}
+
+#[no_mangle]
+unsafe extern "C" fn csmGetDrawableDrawOrders(model: *const csmModel) -> *const c_int {
+    header(model).drawable_draw_orders
+}
+
+#[no_mangle]
+unsafe extern "C" fn csmGetDrawableRenderOrders(model: *const csmModel) -> *const c_int {
+    header(model).drawable_render_orders
+}
+
+#[no_mangle]
+unsafe extern "C" fn csmGetDrawableOpacities(model: *const csmModel) -> *const c_float {
+    header(model).drawable_opacities
+}
+
+#[no_mangle]
+unsafe extern "C" fn csmGetDrawableParentPartIndices(model: *const csmModel) -> *const c_int {
+    header(model).drawable_parts
+}
+
+#[no_mangle]
+unsafe extern "C" fn csmGetDrawableMaskCounts(model: *const csmModel) -> *const c_int {
+    header(model).drawable_mask_counts
+}
+
+#[no_mangle]
+unsafe extern "C" fn csmGetDrawableMasks(model: *const csmModel) -> *mut *const c_int {
+    header(model).drawable_masks
+}
+
+#[no_mangle]
+unsafe extern "C" fn csmGetDrawableVertexCounts(model: *const csmModel) -> *const c_int {
+    header(model).drawable_vertex_counts
+}
+
+#[no_mangle]
+unsafe extern "C" fn csmGetDrawableVertexPositions(
+    model: *const csmModel,
+) -> *mut *const csmVector2 {
+    header(model).drawable_vertex_positions
+}
+
+#[no_mangle]
+unsafe extern "C" fn csmGetDrawableVertexUvs(model: *const csmModel) -> *mut *const csmVector2 {
+    header(model).drawable_vertex_uvs
+}
+
+#[no_mangle]
+unsafe extern "C" fn csmGetDrawableIndexCounts(model: *const csmModel) -> *const c_int {
+    header(model).drawable_index_counts
+}
+
+#[no_mangle]
+unsafe extern "C" fn csmGetDrawableIndices(model: *const csmModel) -> *mut *const c_ushort {
+    header(model).drawable_indices
+}
+
+#[no_mangle]
+unsafe extern "C" fn csmGetDrawableMultiplyColors(model: *const csmModel) -> *const csmVector4 {
+    header(model).drawable_multiply_colors
+}
+
+#[no_mangle]
+unsafe extern "C" fn csmGetDrawableScreenColors(model: *const csmModel) -> *const csmVector4 {
+    header(model).drawable_screen_colors
+}
+
+#[no_mangle]
+unsafe extern "C" fn csmResetDrawableDynamicFlags(model: *mut csmModel) {
+    let h = header(model);
+    for i in 0..h.drawable_count {
+        *h.drawable_dynamic_flags.add(i) &= csmIsVisible;
+    }
+}
+
+#[test]
+fn mock_moc_roundtrip() {
+    let desc = MockMoc::sample();
+    let parsed = parse(&desc.to_bytes()).unwrap();
+    assert_eq!(parsed.desc, desc);
+}
+
+#[test]
+fn mock_moc_rejects_garbage() {
+    let mut bytes = MockMoc::sample().to_bytes();
+    assert!(parse(&bytes[..bytes.len() - 1]).is_none());
+    bytes[0] = b'X';
+    assert!(parse(&bytes).is_none());
+}
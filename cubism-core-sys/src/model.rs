@@ -4,30 +4,42 @@ use moc::csmMoc;
 
 pub const csmAlignofModel: usize = 16;
 
+pub const csmParameterType_Normal: csmParameterType = 0;
+pub const csmParameterType_BlendShape: csmParameterType = 1;
+
+pub type csmParameterType = c_int;
+
 #[repr(C, align(16))]
 pub struct csmModel {
     _unused: [u16; 0],
 }
 
-extern "C" {
-    pub fn csmGetSizeofModel(moc: *const csmMoc) -> c_uint;
-    pub fn csmInitializeModelInPlace(
-        moc: *const csmMoc,
-        aligned_address: *mut c_void,
-        size: c_uint,
-    ) -> *mut csmModel;
-    pub fn csmUpdateModel(model: *mut csmModel);
-
-    pub fn csmGetParameterCount(model: *const csmModel) -> c_int;
-    pub fn csmGetParameterIds(model: *const csmModel) -> *mut *const c_char;
-    pub fn csmGetParameterMinimumValues(model: *const csmModel) -> *const c_float;
-    pub fn csmGetParameterMaximumValues(model: *const csmModel) -> *const c_float;
-    pub fn csmGetParameterDefaultValues(model: *const csmModel) -> *const c_float;
-    pub fn csmGetParameterValues(model: *mut csmModel) -> *mut c_float;
-
-    pub fn csmGetPartCount(model: *const csmModel) -> c_int;
-    pub fn csmGetPartIds(model: *const csmModel) -> *mut *const c_char;
-    pub fn csmGetPartOpacities(model: *mut csmModel) -> *mut c_float;
+csm_functions! {
+    model: ModelFunctions {
+        pub fn csmGetSizeofModel(moc: *const csmMoc) -> c_uint;
+        pub fn csmInitializeModelInPlace(
+            moc: *const csmMoc,
+            aligned_address: *mut c_void,
+            size: c_uint,
+        ) -> *mut csmModel;
+        pub fn csmUpdateModel(model: *mut csmModel);
+
+        pub fn csmGetParameterCount(model: *const csmModel) -> c_int;
+        pub fn csmGetParameterIds(model: *const csmModel) -> *mut *const c_char;
+        pub fn csmGetParameterMinimumValues(model: *const csmModel) -> *const c_float;
+        pub fn csmGetParameterMaximumValues(model: *const csmModel) -> *const c_float;
+        pub fn csmGetParameterDefaultValues(model: *const csmModel) -> *const c_float;
+        pub fn csmGetParameterValues(model: *mut csmModel) -> *mut c_float;
+        pub fn csmGetParameterTypes(model: *const csmModel) -> *const csmParameterType;
+        pub fn csmGetParameterKeyCounts(model: *const csmModel) -> *const c_int;
+        pub fn csmGetParameterKeyValues(model: *const csmModel) -> *mut *const c_float;
+        pub fn csmGetParameterRepeats(model: *const csmModel) -> *const c_int;
+
+        pub fn csmGetPartCount(model: *const csmModel) -> c_int;
+        pub fn csmGetPartIds(model: *const csmModel) -> *mut *const c_char;
+        pub fn csmGetPartOpacities(model: *mut csmModel) -> *mut c_float;
+        pub fn csmGetPartParentPartIndices(model: *const csmModel) -> *const c_int;
+    }
 }
 
 #[test]
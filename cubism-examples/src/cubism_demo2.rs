@@ -91,21 +91,21 @@ fn main() {
     let parameter_names = {
         let mut vec = Vec::with_capacity(model.parameter_count());
         for idx in 0..model.parameter_count() {
-            vec.push(unsafe { ImString::from_utf8_with_nul_unchecked([model.parameter_ids()[idx], "\0"].concat().into_bytes()) })
+            vec.push(unsafe { ImString::from_utf8_with_nul_unchecked([model.parameter_ids()[idx].as_str(), "\0"].concat().into_bytes()) })
         }
         vec
     };
     let part_names = {
         let mut vec = Vec::with_capacity(model.part_count());
         for idx in 0..model.part_count() {
-            vec.push(unsafe { ImString::from_utf8_with_nul_unchecked([model.part_ids()[idx], "\0"].concat().into_bytes()) })
+            vec.push(unsafe { ImString::from_utf8_with_nul_unchecked([model.part_ids()[idx].as_str(), "\0"].concat().into_bytes()) })
         }
         vec
     };
     let drawable_names = {
         let mut vec = Vec::with_capacity(model.drawable_count());
         for idx in 0..model.drawable_count() {
-            vec.push(unsafe { ImString::from_utf8_with_nul_unchecked([model.drawable_ids()[idx], "\0"].concat().into_bytes()) })
+            vec.push(unsafe { ImString::from_utf8_with_nul_unchecked([model.drawable_ids()[idx].as_str(), "\0"].concat().into_bytes()) })
         }
         vec
     };
@@ -3,8 +3,13 @@
 in vec4 v_color;
 in vec2 v_tex_coord;
 uniform sampler2D tex;
+uniform vec4 u_multiply_color;
+uniform vec4 u_screen_color;
 out vec4 Target0;
 
 void main() {
-    Target0 = texture(tex, v_tex_coord);
+    vec4 color = texture(tex, v_tex_coord);
+    color.rgb *= u_multiply_color.rgb;
+    color.rgb = color.rgb + u_screen_color.rgb - color.rgb * u_screen_color.rgb;
+    Target0 = color;
 }
@@ -3,7 +3,7 @@ extern crate cubism;
 extern crate gfx;
 extern crate nalgebra as na;
 
-use cubism::Model;
+use cubism::{Drawable, Model};
 use na::Matrix4;
 
 use gfx::handle::{Buffer, RenderTargetView};
@@ -94,6 +94,8 @@ gfx_defines!{
         vertex_buffer: gfx::VertexBuffer<Vertex> = (),
         tex: TextureSampler<[f32; 4]> = "tex",
         mvp: gfx::Global<[[f32; 4]; 4]> = "u_mvp",
+        multiply_color: gfx::Global<[f32; 4]> = "u_multiply_color",
+        screen_color: gfx::Global<[f32; 4]> = "u_screen_color",
         out: gfx::BlendTarget<ColorFormat> = (
             "Target0",
             gfx::state::ColorMask::all(),
@@ -157,6 +159,8 @@ impl<R: Resources> Renderer<R> {
             vertex_buffer,
             tex: (texture, sampler),
             mvp: [[0.0; 4]; 4],
+            multiply_color: [1.0; 4],
+            screen_color: [0.0, 0.0, 0.0, 1.0],
             out: target,
         };
         copy_unsized_to_fixedsize(Matrix4::<f32>::identity().as_slice(), &mut data.mvp);
@@ -187,17 +191,12 @@ impl<R: Resources> Renderer<R> {
     ) -> RendererResult<()> {
         self.bundle.data.tex = (texture.clone(), sampler.clone());
 
-        let mut sorted_draw_indices = vec![0; model.drawable_count()];
-        for (idx, order) in model.drawable_render_orders().iter().enumerate() {
-            sorted_draw_indices[*order as usize] = idx;
-        }
-
         copy_unsized_to_fixedsize(self.mvp.as_slice(), &mut self.bundle.data.mvp);
 
-        for draw_idx in sorted_draw_indices {
+        for drawable in model.drawables_in_render_order() {
             //set clipping mask
 
-            self.draw_mesh(factory, encoder, model, draw_idx).unwrap();
+            self.draw_mesh(factory, encoder, drawable).unwrap();
         }
         Ok(())
     }
@@ -206,16 +205,13 @@ impl<R: Resources> Renderer<R> {
         &mut self,
         factory: &mut F,
         encoder: &mut Encoder<R, C>,
-        model: &Model,
-        index: usize,
+        drawable: Drawable,
     ) -> RendererResult<()> {
-        let opacity = model.drawable_opacities()[index];
-        if opacity <= 0.0 {
+        if drawable.opacity() <= 0.0 {
             return Ok(());
         }
-        //let vtx_index_count = model.drawable_index_counts()[index];
-        let vtx_pos = model.drawable_vertex_positions(index);
-        let vtx_uv = model.drawable_vertex_uvs(index);
+        let vtx_pos = drawable.vertex_positions();
+        let vtx_uv = drawable.vertex_uvs();
         let mut vtx_buffer = Vec::with_capacity(vtx_pos.len());
         for i in 0..vtx_pos.len() {
             let vtx_pos = vtx_pos[i];
@@ -226,7 +222,9 @@ impl<R: Resources> Renderer<R> {
                 color: [1.0, 1.0, 1.0],
             });
         }
-        let idx_buffer = Vec::from(model.drawable_indices(index));
+        let idx_buffer = Vec::from(drawable.indices());
+        self.bundle.data.multiply_color = drawable.multiply_color();
+        self.bundle.data.screen_color = drawable.screen_color();
         self.upload_vertex_buffer(factory, encoder, &vtx_buffer)?;
         self.upload_index_buffer(factory, encoder, &idx_buffer)?;
 
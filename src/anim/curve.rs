@@ -0,0 +1,282 @@
+//! Animation curves and their segments
+use std::f32::consts::PI;
+
+// a point of a curve, a time in seconds and the value at that time
+type Point = (f32, f32);
+
+/// What a [Curve](./struct.Curve.html) animates.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+pub enum CurveTarget {
+    /// A value of the model as a whole, like its `Opacity`.
+    Model,
+    /// The value of a parameter.
+    Parameter,
+    /// The opacity of a part.
+    PartOpacity,
+}
+
+/// A segment of a curve, containing the `(time, value)` points following the end point of the previous segment.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Segment {
+    /// Linearly interpolates to the point.
+    Linear(Point),
+    /// A cubic bezier curve with two control points and the end point.
+    Bezier(Point, Point, Point),
+    /// Keeps the previous value until the point is reached.
+    Stepped(Point),
+    /// Jumps to the value of the point right after the previous point.
+    InverseStepped(Point),
+}
+
+impl Segment {
+    /// Returns the end point of this segment.
+    #[inline]
+    pub fn end(&self) -> Point {
+        match *self {
+            Segment::Linear(p)
+            | Segment::Bezier(_, _, p)
+            | Segment::Stepped(p)
+            | Segment::InverseStepped(p) => p,
+        }
+    }
+
+    /// Evaluates this segment starting at `start` at `time`.
+    ///
+    /// With `beziers_restricted` the time of bezier segments is interpolated linearly, otherwise
+    /// the curve is solved for the time like in the editor.
+    pub fn evaluate(&self, start: Point, time: f32, beziers_restricted: bool) -> f32 {
+        match *self {
+            Segment::Linear(end) => lerp(start.1, end.1, progress(start, end, time)),
+            Segment::Bezier(c1, c2, end) => {
+                let t = if beziers_restricted {
+                    progress(start, end, time)
+                } else {
+                    solve_bezier_time(start.0, c1.0, c2.0, end.0, time)
+                };
+                bezier(start.1, c1.1, c2.1, end.1, t)
+            }
+            Segment::Stepped(_) => start.1,
+            Segment::InverseStepped(end) => end.1,
+        }
+    }
+}
+
+/// An animation curve of a [Motion](./struct.Motion.html).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Curve {
+    /// What this curve animates.
+    pub target: CurveTarget,
+    /// The id of the animated parameter or part, or the name of the model value.
+    pub id: String,
+    /// The fade in time of this curve in seconds, overriding the one of the motion.
+    pub fade_in_time: Option<f32>,
+    /// The fade out time of this curve in seconds, overriding the one of the motion.
+    pub fade_out_time: Option<f32>,
+    /// The first point of this curve.
+    pub start: Point,
+    /// The segments of this curve, in order.
+    pub segments: Vec<Segment>,
+}
+
+impl Curve {
+    /// Returns the value of this curve at `time`.
+    ///
+    /// Times before the first point evaluate the first segment at its start, times after the last point
+    /// return the value of the last point.
+    pub fn evaluate(&self, time: f32, beziers_restricted: bool) -> f32 {
+        let mut start = self.start;
+        for segment in &self.segments {
+            let end = segment.end();
+            if end.0 > time {
+                return segment.evaluate(start, time, beziers_restricted);
+            }
+            start = end;
+        }
+        start.1
+    }
+
+    /// Parses the flat `Segments` array of a motion3.json curve, returns `None` if it is malformed.
+    pub(crate) fn parse_segments(data: &[f32]) -> Option<(Point, Vec<Segment>)> {
+        fn point(data: &[f32], i: usize) -> Option<Point> {
+            Some((*data.get(i)?, *data.get(i + 1)?))
+        }
+        let start = point(data, 0)?;
+        let mut segments = Vec::new();
+        let mut i = 2;
+        while i < data.len() {
+            let (segment, len) = match data[i] as i32 {
+                0 => (Segment::Linear(point(data, i + 1)?), 2),
+                1 => (
+                    Segment::Bezier(
+                        point(data, i + 1)?,
+                        point(data, i + 3)?,
+                        point(data, i + 5)?,
+                    ),
+                    6,
+                ),
+                2 => (Segment::Stepped(point(data, i + 1)?), 2),
+                3 => (Segment::InverseStepped(point(data, i + 1)?), 2),
+                _ => return None,
+            };
+            segments.push(segment);
+            i += len + 1;
+        }
+        Some((start, segments))
+    }
+}
+
+#[inline]
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+fn progress(start: Point, end: Point, time: f32) -> f32 {
+    let t = (time - start.0) / (end.0 - start.0);
+    if t.is_nan() {
+        0.0
+    } else {
+        t.max(0.0)
+    }
+}
+
+fn bezier(p0: f32, p1: f32, p2: f32, p3: f32, t: f32) -> f32 {
+    let p01 = lerp(p0, p1, t);
+    let p12 = lerp(p1, p2, t);
+    let p23 = lerp(p2, p3, t);
+    let p012 = lerp(p01, p12, t);
+    let p123 = lerp(p12, p23, t);
+    lerp(p012, p123, t)
+}
+
+const EPSILON: f32 = 0.00001;
+
+/// Finds the bezier parameter at which the curve with the time coordinates `x0..x3` reaches `time`,
+/// using Cardano's formula like the official framework.
+fn solve_bezier_time(x0: f32, x1: f32, x2: f32, x3: f32, time: f32) -> f32 {
+    let a = x3 - 3.0 * x2 + 3.0 * x1 - x0;
+    let b = 3.0 * x2 - 6.0 * x1 + 3.0 * x0;
+    let c = 3.0 * x1 - 3.0 * x0;
+    let d = x0 - time;
+    let clamp = |t: f32| t.clamp(0.0, 1.0);
+
+    if a.abs() < EPSILON {
+        return clamp(solve_quadratic(b, c, d));
+    }
+    let (ba, ca, da) = (b / a, c / a, d / a);
+    let p = (3.0 * ca - ba * ba) / 3.0;
+    let p3 = p / 3.0;
+    let q = (2.0 * ba * ba * ba - 9.0 * ba * ca + 27.0 * da) / 27.0;
+    let q2 = q / 2.0;
+    let discriminant = q2 * q2 + p3 * p3 * p3;
+
+    // the root closest to the middle of the segment is the one that lies within 0..1
+    let center = 0.5;
+    let threshold = center + 0.01;
+    let near_center = |root: f32| (root - center).abs() < threshold;
+
+    if discriminant < 0.0 {
+        let mp3 = -p / 3.0;
+        let r = (mp3 * mp3 * mp3).sqrt();
+        let phi = (-q / (2.0 * r)).clamp(-1.0, 1.0).acos();
+        let t1 = 2.0 * r.cbrt();
+        let root1 = t1 * (phi / 3.0).cos() - ba / 3.0;
+        if near_center(root1) {
+            return clamp(root1);
+        }
+        let root2 = t1 * ((phi + 2.0 * PI) / 3.0).cos() - ba / 3.0;
+        if near_center(root2) {
+            return clamp(root2);
+        }
+        let root3 = t1 * ((phi + 4.0 * PI) / 3.0).cos() - ba / 3.0;
+        return clamp(root3);
+    }
+    if discriminant == 0.0 {
+        let u1 = if q2 < 0.0 { (-q2).cbrt() } else { -q2.cbrt() };
+        let root1 = 2.0 * u1 - ba / 3.0;
+        if near_center(root1) {
+            return clamp(root1);
+        }
+        return clamp(-u1 - ba / 3.0);
+    }
+    let sd = discriminant.sqrt();
+    let u1 = (sd - q2).cbrt();
+    let v1 = (sd + q2).cbrt();
+    clamp(u1 - v1 - ba / 3.0)
+}
+
+fn solve_quadratic(a: f32, b: f32, c: f32) -> f32 {
+    if a.abs() < EPSILON {
+        if b.abs() < EPSILON {
+            return -c;
+        }
+        return -c / b;
+    }
+    -(b + (b * b - 4.0 * a * c).sqrt()) / (2.0 * a)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Curve, CurveTarget, Segment};
+
+    fn parse(data: &[f32]) -> Curve {
+        let (start, segments) = Curve::parse_segments(data).unwrap();
+        Curve {
+            target: CurveTarget::Parameter,
+            id: "Param".to_owned(),
+            fade_in_time: None,
+            fade_out_time: None,
+            start,
+            segments,
+        }
+    }
+
+    #[test]
+    fn parse_segments() {
+        let curve = parse(&[
+            0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 1.5, 1.0, 2.0, 0.0, 3.0, 0.0, 2.0, 4.0, 1.0, 3.0, 5.0,
+            0.0,
+        ]);
+        assert_eq!(curve.start, (0.0, 0.0));
+        assert_eq!(
+            curve.segments,
+            vec![
+                Segment::Linear((1.0, 1.0)),
+                Segment::Bezier((1.5, 1.0), (2.0, 0.0), (3.0, 0.0)),
+                Segment::Stepped((4.0, 1.0)),
+                Segment::InverseStepped((5.0, 0.0)),
+            ]
+        );
+        assert_eq!(Curve::parse_segments(&[]), None);
+        assert_eq!(Curve::parse_segments(&[0.0, 0.0, 0.0, 1.0]), None);
+        assert_eq!(Curve::parse_segments(&[0.0, 0.0, 4.0, 1.0, 1.0]), None);
+    }
+
+    #[test]
+    fn evaluate_segments() {
+        let curve = parse(&[
+            0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 1.5, 1.0, 2.5, 0.0, 3.0, 0.0, 2.0, 4.0, 1.0, 3.0, 5.0,
+            0.0,
+        ]);
+        for &restricted in &[true, false] {
+            assert_eq!(curve.evaluate(-1.0, restricted), 0.0);
+            assert_eq!(curve.evaluate(0.5, restricted), 0.5);
+            assert_eq!(curve.evaluate(1.0, restricted), 1.0);
+            assert_eq!(curve.evaluate(3.5, restricted), 0.0);
+            assert_eq!(curve.evaluate(4.5, restricted), 0.0);
+            assert_eq!(curve.evaluate(10.0, restricted), 0.0);
+        }
+        // the bezier ease is symmetric around its middle
+        assert!((curve.evaluate(2.0, true) - 0.5).abs() < 1e-5);
+        assert!((curve.evaluate(2.0, false) - 0.5).abs() < 1e-5);
+
+        // with control points spread evenly in time both interpretations match
+        let even = parse(&[0.0, 0.0, 1.0, 1.0, 1.0, 2.0, -1.0, 3.0, 0.0]);
+        for &time in &[0.25, 0.5, 1.5, 2.5, 2.9] {
+            let (a, b) = (even.evaluate(time, true), even.evaluate(time, false));
+            assert!((a - b).abs() < 1e-4, "{} != {} at {}", a, b, time);
+        }
+        // otherwise they differ
+        let uneven = parse(&[0.0, 0.0, 1.0, 0.1, 1.0, 0.2, 1.0, 3.0, 1.0]);
+        assert!(uneven.evaluate(0.5, false) > uneven.evaluate(0.5, true));
+    }
+}
@@ -0,0 +1,385 @@
+//! The exp3.json expression file and the blending of expressions
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::sync::Arc;
+
+use serde_json;
+
+use super::manager::easing_sine;
+use mdl::Model;
+use {CubismError, Result};
+
+/// How the value of an [ExpressionParameter](./struct.ExpressionParameter.html) is combined with the
+/// value of the parameter.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Deserialize)]
+pub enum ExpressionBlend {
+    /// The value is added to the parameter.
+    #[default]
+    Add,
+    /// The parameter is multiplied with the value.
+    Multiply,
+    /// The value replaces the parameter.
+    Overwrite,
+}
+
+/// A parameter changed by an [Expression](./struct.Expression.html).
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ExpressionParameter {
+    /// The id of the parameter.
+    pub id: String,
+    /// The value that is blended with the parameter.
+    #[serde(default)]
+    pub value: f32,
+    /// How the value is blended with the parameter.
+    #[serde(default)]
+    pub blend: ExpressionBlend,
+}
+
+/// A facial expression loaded from an exp3.json file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Expression {
+    /// The fade in time of this expression in seconds.
+    pub fade_in_time: f32,
+    /// The fade out time of this expression in seconds.
+    pub fade_out_time: f32,
+    /// The parameters changed by this expression.
+    pub parameters: Vec<ExpressionParameter>,
+}
+
+impl Expression {
+    /// Loads the exp3.json file at `path`.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_reader(&mut File::open(path)?)
+    }
+
+    /// Parses an expression from a reader instance.
+    pub fn from_reader<R: Read>(reader: &mut R) -> Result<Self> {
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf)?;
+        Self::from_slice(&buf)
+    }
+
+    /// Parses an expression from json data, missing fade times default to 1 second.
+    pub fn from_slice(data: &[u8]) -> Result<Self> {
+        let json: Exp3Json = serde_json::from_slice(data)?;
+        if json.kind != "Live2D Expression" {
+            return Err(CubismError::InvalidJson(format!(
+                "unsupported exp3.json type {}",
+                json.kind
+            )));
+        }
+        let expression = Expression {
+            fade_in_time: json.fade_in_time.unwrap_or(1.0),
+            fade_out_time: json.fade_out_time.unwrap_or(1.0),
+            parameters: json.parameters,
+        };
+        for &fade in &[expression.fade_in_time, expression.fade_out_time] {
+            if fade < 0.0 || fade.is_nan() {
+                return Err(CubismError::InvalidJson(format!(
+                    "invalid fade time {}",
+                    fade
+                )));
+            }
+        }
+        Ok(expression)
+    }
+
+    /// Returns the parameter with the specified id.
+    pub fn parameter(&self, id: &str) -> Option<&ExpressionParameter> {
+        self.parameters.iter().find(|param| param.id == id)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Exp3Json {
+    #[serde(rename = "Type")]
+    kind: String,
+    #[serde(default)]
+    fade_in_time: Option<f32>,
+    #[serde(default)]
+    fade_out_time: Option<f32>,
+    #[serde(default)]
+    parameters: Vec<ExpressionParameter>,
+}
+
+#[derive(Clone, Debug)]
+struct Entry {
+    expression: Arc<Expression>,
+    // the start time is set by the first update after the expression was started
+    started: bool,
+    start_time: f32,
+    end_time: Option<f32>,
+}
+
+impl Entry {
+    fn fade_out(&mut self, time: f32) {
+        let end = time + self.expression.fade_out_time;
+        match self.end_time {
+            Some(old) if old <= end => (),
+            _ => self.end_time = Some(end),
+        }
+    }
+
+    fn fade_in(&self, time: f32) -> f32 {
+        if self.expression.fade_in_time == 0.0 {
+            1.0
+        } else {
+            easing_sine((time - self.start_time) / self.expression.fade_in_time)
+        }
+    }
+
+    fn weight(&self, time: f32) -> f32 {
+        let fade_in = self.fade_in(time);
+        let fade_out = match self.end_time {
+            Some(_) if self.expression.fade_out_time == 0.0 => 0.0,
+            Some(end) => easing_sine((end - time) / self.expression.fade_out_time),
+            None => 1.0,
+        };
+        fade_in * fade_out
+    }
+}
+
+// the accumulated effect of the expressions on a parameter
+#[derive(Clone, Copy, Debug)]
+struct Blend {
+    add: f32,
+    multiply: f32,
+    overwrite: f32,
+}
+
+impl Blend {
+    fn lerp(&mut self, other: Blend, weight: f32) {
+        let lerp = |a: f32, b: f32| a + (b - a) * weight;
+        self.add = lerp(self.add, other.add);
+        self.multiply = lerp(self.multiply, other.multiply);
+        self.overwrite = lerp(self.overwrite, other.overwrite);
+    }
+}
+
+/// Plays expressions on a model, fading between them like the official framework.
+///
+/// Starting an expression fades out the active ones while the new one fades in. The expressions are
+/// blended in the order they were started: the oldest one sets the added, multiplied and overwritten
+/// amounts of a parameter, every later one moves them towards its own by its fade, parameters an
+/// expression lacks move back towards no change. The result `(overwrite + add) * multiply` is blended
+/// with the current value by the sum of the fade ins. Once the newest expression has fully faded in the
+/// older ones are removed.
+///
+/// Like in the framework, the fade out of the last remaining expression is not visible, it stays
+/// applied until its fade out time has passed.
+#[derive(Clone, Debug, Default)]
+pub struct ExpressionManager {
+    entries: Vec<Entry>,
+    time: f32,
+}
+
+impl ExpressionManager {
+    /// Creates a new manager without any active expression.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fades in `expression`, fading out all active expressions.
+    pub fn start(&mut self, expression: Arc<Expression>) {
+        self.stop_all();
+        self.entries.push(Entry {
+            expression,
+            started: false,
+            start_time: self.time,
+            end_time: None,
+        });
+    }
+
+    /// Fades out all active expressions, the last one is removed once its fade out time has passed.
+    pub fn stop_all(&mut self) {
+        let time = self.time;
+        for entry in &mut self.entries {
+            entry.fade_out(time);
+        }
+    }
+
+    /// Removes all expressions immediately.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Returns true if no expression is active.
+    #[inline]
+    pub fn is_finished(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Advances time by `delta` seconds and applies the active expressions to `model`.
+    ///
+    /// Expressions started since the last update begin fading in at the new time.
+    /// Expressions are applied on top of the parameter values already in the model, so these should be
+    /// restored to their base values or set by motions before every update.
+    pub fn update(&mut self, model: &mut Model, delta: f32) {
+        self.time += delta;
+        let time = self.time;
+        self.entries.retain(|entry| match entry.end_time {
+            Some(end) => end >= time,
+            None => true,
+        });
+        if self.entries.is_empty() {
+            return;
+        }
+        for entry in self.entries.iter_mut().filter(|entry| !entry.started) {
+            entry.started = true;
+            entry.start_time = time;
+        }
+
+        let weights: Vec<f32> = self
+            .entries
+            .iter()
+            .map(|entry| entry.weight(time))
+            .collect();
+        let total = self
+            .entries
+            .iter()
+            .map(|entry| entry.fade_in(time))
+            .sum::<f32>()
+            .min(1.0);
+        let mut ids: Vec<&str> = Vec::new();
+        for entry in &self.entries {
+            for param in &entry.expression.parameters {
+                if !ids.contains(&&*param.id) {
+                    ids.push(&param.id);
+                }
+            }
+        }
+        for id in ids {
+            let mut param = match model.parameter_mut(id) {
+                Some(param) => param,
+                None => continue,
+            };
+            let current = param.value();
+            let unchanged = Blend {
+                add: 0.0,
+                multiply: 1.0,
+                overwrite: current,
+            };
+            let mut blend = unchanged;
+            for (i, (entry, &weight)) in self.entries.iter().zip(&weights).enumerate() {
+                let mut target = unchanged;
+                if let Some(param) = entry.expression.parameter(id) {
+                    match param.blend {
+                        ExpressionBlend::Add => target.add = param.value,
+                        ExpressionBlend::Multiply => target.multiply = param.value,
+                        ExpressionBlend::Overwrite => target.overwrite = param.value,
+                    }
+                }
+                // the oldest expression is applied without its fade
+                blend.lerp(target, if i == 0 { 1.0 } else { weight });
+            }
+            param.set_value_weighted((blend.overwrite + blend.add) * blend.multiply, total);
+        }
+
+        let newest = self.entries.len() - 1;
+        if weights[newest] >= 1.0 {
+            self.entries.drain(..newest);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Expression, ExpressionBlend};
+    use CubismError;
+
+    const EXP3: &str = r#"{
+        "Type": "Live2D Expression",
+        "FadeInTime": 0.5,
+        "Parameters": [
+            {"Id": "ParamAngleX", "Value": 10},
+            {"Id": "ParamArmX", "Value": 0.5, "Blend": "Multiply"},
+            {"Id": "ParamMissing", "Value": 1, "Blend": "Overwrite"}
+        ]
+    }"#;
+
+    #[test]
+    fn expression_from_json() {
+        let exp = Expression::from_slice(EXP3.as_bytes()).unwrap();
+        assert_eq!(exp.fade_in_time, 0.5);
+        assert_eq!(exp.fade_out_time, 1.0);
+        assert_eq!(exp.parameters.len(), 3);
+        assert_eq!(exp.parameters[0].blend, ExpressionBlend::Add);
+        assert_eq!(exp.parameter("ParamArmX").unwrap().value, 0.5);
+        assert_eq!(
+            exp.parameter("ParamMissing").unwrap().blend,
+            ExpressionBlend::Overwrite
+        );
+
+        let invalid = [
+            EXP3.replace("Live2D Expression", "Live2D Pose"),
+            EXP3.replace(r#""FadeInTime": 0.5"#, r#""FadeInTime": -0.5"#),
+        ];
+        for json in &invalid {
+            match Expression::from_slice(json.as_bytes()) {
+                Err(CubismError::InvalidJson(_)) => (),
+                other => panic!("unexpected result {:?}", other),
+            }
+        }
+    }
+
+    #[cfg(feature = "mock-core")]
+    #[test]
+    fn blend_expressions() {
+        use super::{ExpressionManager, ExpressionParameter};
+        use core::mock::MockMoc;
+        use mdl::Model;
+        use std::sync::Arc;
+
+        fn update(manager: &mut ExpressionManager, model: &mut Model, delta: f32) -> (f32, f32) {
+            model.reset_parameters();
+            manager.update(model, delta);
+            (
+                model.parameter_value("ParamAngleX"),
+                model.parameter_value("ParamArmX"),
+            )
+        }
+        fn assert_near((a, b): (f32, f32), (c, d): (f32, f32)) {
+            assert!(
+                (a - c).abs() < 1e-4 && (b - d).abs() < 1e-4,
+                "{:?} != {:?}",
+                (a, b),
+                (c, d)
+            );
+        }
+
+        let mut model = Model::from_bytes(&MockMoc::sample().to_bytes()).unwrap();
+        let mut manager = ExpressionManager::new();
+        let mut first = Expression::from_slice(EXP3.as_bytes()).unwrap();
+        first.fade_in_time = 0.0;
+        let second = Expression {
+            fade_in_time: 1.0,
+            fade_out_time: 1.0,
+            parameters: vec![ExpressionParameter {
+                id: "ParamAngleX".to_owned(),
+                value: 20.0,
+                blend: ExpressionBlend::Overwrite,
+            }],
+        };
+
+        manager.start(Arc::new(first));
+        assert_near(update(&mut manager, &mut model, 0.5), (10.0, 0.25));
+
+        // the second one starts fading in on its first update, at half weight the angle is
+        // overwritten halfway to 20 with half of the added 10 left, the arm's multiplier is halfway
+        // back to 1
+        manager.start(Arc::new(second));
+        assert_near(update(&mut manager, &mut model, 0.5), (10.0, 0.25));
+        assert_near(update(&mut manager, &mut model, 0.5), (15.0, 0.375));
+        assert_near(update(&mut manager, &mut model, 0.5), (20.0, 0.5));
+        assert_near(update(&mut manager, &mut model, 0.5), (20.0, 0.5));
+
+        // the last expression stays applied until its fade out time has passed
+        manager.stop_all();
+        assert_near(update(&mut manager, &mut model, 0.5), (20.0, 0.5));
+        update(&mut manager, &mut model, 0.6);
+        assert!(manager.is_finished());
+    }
+}
@@ -0,0 +1,510 @@
+//! Playback and crossfading of motions
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+use super::motion::Motion;
+use mdl::Model;
+
+/// The priority of a motion played by a [MotionManager](./struct.MotionManager.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MotionPriority {
+    /// A motion played while nothing else is going on.
+    Idle = 1,
+    /// A motion played in reaction to something.
+    Normal = 2,
+    /// A motion that is played regardless of what is currently playing.
+    Force = 3,
+}
+
+/// A handle to a motion started by a [MotionManager](./struct.MotionManager.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct MotionHandle(u64);
+
+/// An event of a motion that was crossed during the last
+/// [MotionManager::update](./struct.MotionManager.html#method.update).
+#[derive(Clone, Debug, PartialEq)]
+pub struct FiredEvent {
+    /// The handle of the motion the event belongs to.
+    pub handle: MotionHandle,
+    /// The time of the event within the motion in seconds.
+    pub time: f32,
+    /// The contents of the event.
+    pub value: String,
+}
+
+#[derive(Clone, Debug)]
+struct Entry {
+    handle: MotionHandle,
+    motion: Arc<Motion>,
+    // the times are set by the first update after the motion was started
+    started: bool,
+    start_time: f32,
+    fade_in_start_time: f32,
+    end_time: Option<f32>,
+    fading_out: bool,
+    // events at or after this time haven't fired yet
+    event_cursor: f32,
+}
+
+impl Entry {
+    fn fade_out(&mut self, time: f32) {
+        let end = time + self.motion.fade_out_time();
+        self.fading_out = true;
+        match self.end_time {
+            Some(old) if old <= end => (),
+            _ => self.end_time = Some(end),
+        }
+    }
+
+    fn fire_events(&mut self, to: f32, fired: &mut Vec<FiredEvent>) {
+        let handle = self.handle;
+        let events = self.motion.events_between(self.event_cursor, to);
+        fired.extend(events.iter().map(|event| FiredEvent {
+            handle,
+            time: event.time,
+            value: event.value.clone(),
+        }));
+        self.event_cursor = to;
+    }
+}
+
+/// Plays motions on a model, crossfading between them, following the queue and priority semantics of
+/// the official framework.
+///
+/// Starting a motion fades out all playing motions while the new one fades in. Whether a motion may
+/// start is decided by its priority: it has to be higher than the priority of the playing motion and
+/// of any reservation, only [Force](enum.MotionPriority.html#variant.Force) motions always start.
+///
+/// The events of the motions are collected during every update, see [events](#method.events).
+#[derive(Clone, Debug, Default)]
+pub struct MotionManager {
+    entries: Vec<Entry>,
+    events: Vec<FiredEvent>,
+    time: f32,
+    next_handle: u64,
+    current_priority: Option<MotionPriority>,
+    reserve_priority: Option<MotionPriority>,
+}
+
+impl MotionManager {
+    /// Creates a new manager that isn't playing anything.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the priority of the playing motion or `None` if all motions finished.
+    #[inline]
+    pub fn current_priority(&self) -> Option<MotionPriority> {
+        self.current_priority
+    }
+
+    /// Returns the priority that is currently reserved.
+    #[inline]
+    pub fn reserve_priority(&self) -> Option<MotionPriority> {
+        self.reserve_priority
+    }
+
+    /// Reserves `priority` for a motion that is about to start, for example while it is still loading.
+    ///
+    /// Returns false if the priority is not higher than the playing or the reserved priority, in which case
+    /// the motion should not be started.
+    pub fn reserve(&mut self, priority: MotionPriority) -> bool {
+        if Some(priority) <= self.reserve_priority || Some(priority) <= self.current_priority {
+            return false;
+        }
+        self.reserve_priority = Some(priority);
+        true
+    }
+
+    /// Starts playing `motion` if `priority` allows it, fading out all playing motions.
+    ///
+    /// A motion started with the reserved priority consumes the reservation.
+    pub fn start(&mut self, motion: Arc<Motion>, priority: MotionPriority) -> Option<MotionHandle> {
+        if priority != MotionPriority::Force
+            && Some(priority) != self.reserve_priority
+            && !self.reserve(priority)
+        {
+            return None;
+        }
+        if Some(priority) == self.reserve_priority {
+            self.reserve_priority = None;
+        }
+        self.current_priority = Some(priority);
+
+        let time = self.time;
+        for entry in &mut self.entries {
+            entry.fade_out(time);
+        }
+        let handle = MotionHandle(self.next_handle);
+        self.next_handle += 1;
+        self.entries.push(Entry {
+            handle,
+            motion,
+            started: false,
+            start_time: time,
+            fade_in_start_time: time,
+            end_time: None,
+            fading_out: false,
+            event_cursor: 0.0,
+        });
+        Some(handle)
+    }
+
+    /// Returns true if the motion started with `handle` is still playing.
+    pub fn is_playing(&self, handle: MotionHandle) -> bool {
+        self.entries.iter().any(|entry| entry.handle == handle)
+    }
+
+    /// Returns true if no motion is playing.
+    #[inline]
+    pub fn is_finished(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Fades out the motion started with `handle`.
+    pub fn stop(&mut self, handle: MotionHandle) {
+        let time = self.time;
+        for entry in self
+            .entries
+            .iter_mut()
+            .filter(|entry| entry.handle == handle)
+        {
+            entry.fade_out(time);
+        }
+    }
+
+    /// Moves the motion started with `handle` to `time` seconds into the motion.
+    ///
+    /// Times past the duration of a looping motion wrap around. No events fire for the skipped time, the
+    /// events at `time` and later fire as playback continues.
+    pub fn seek(&mut self, handle: MotionHandle, time: f32) {
+        let now = self.time;
+        for entry in self
+            .entries
+            .iter_mut()
+            .filter(|entry| entry.handle == handle)
+        {
+            let duration = entry.motion.meta.duration;
+            let time = if entry.motion.meta.looping && duration > 0.0 {
+                time.max(0.0) % duration
+            } else {
+                time.max(0.0)
+            };
+            entry.start_time = now - time;
+            entry.event_cursor = time;
+            if entry.started && !entry.fading_out && !entry.motion.meta.looping {
+                entry.end_time = Some(entry.start_time + entry.motion.meta.duration);
+            }
+        }
+    }
+
+    /// Returns the events crossed by the playing motions during the last update, in the order they occurred
+    /// for each motion.
+    ///
+    /// Every event fires once each time its motion passes it, including once per loop of a looping motion,
+    /// but at most once per update even if several loops were skipped.
+    #[inline]
+    pub fn events(&self) -> &[FiredEvent] {
+        &self.events
+    }
+
+    /// Stops all motions immediately.
+    pub fn stop_all(&mut self) {
+        self.entries.clear();
+        self.current_priority = None;
+    }
+
+    /// Advances time by `delta` seconds and applies the playing motions to `model`, older ones first.
+    ///
+    /// Motions started since the last update begin playing at the new time, so their first frame is
+    /// applied and their fade in starts now.
+    /// Motions blend with the parameter values already in the model, so these should be restored to
+    /// their base values before every update. Returns false if no motion was applied.
+    pub fn update(&mut self, model: &mut Model, delta: f32) -> bool {
+        self.time += delta;
+        self.events.clear();
+        let time = self.time;
+        let updated = !self.entries.is_empty();
+        for entry in &mut self.entries {
+            let motion = entry.motion.clone();
+            let duration = motion.meta.duration;
+            if !entry.started {
+                entry.started = true;
+                // a seek before the first update is kept in the event cursor
+                entry.start_time = time - entry.event_cursor;
+                entry.fade_in_start_time = time;
+                if !entry.fading_out && !motion.meta.looping {
+                    entry.end_time = Some(entry.start_time + duration);
+                }
+            }
+            let mut offset = time - entry.start_time;
+            if motion.meta.looping && duration > 0.0 && offset >= duration {
+                let loops = (offset / duration).floor();
+                let cursor = entry.event_cursor;
+                entry.start_time += loops * duration;
+                offset -= loops * duration;
+                // finish the current loop, then start the new one without firing any event twice
+                entry.fire_events(f32::INFINITY, &mut self.events);
+                entry.event_cursor = 0.0;
+                entry.fire_events(offset.min(cursor), &mut self.events);
+                entry.event_cursor = offset;
+                if motion.loop_fade_in {
+                    entry.fade_in_start_time = entry.start_time;
+                }
+            }
+            entry.fire_events(offset, &mut self.events);
+
+            let fade_in = |fade_time: f32| {
+                if fade_time == 0.0 {
+                    1.0
+                } else {
+                    easing_sine((time - entry.fade_in_start_time) / fade_time)
+                }
+            };
+            let fade_out = |fade_time: f32| match entry.end_time {
+                Some(end) if fade_time != 0.0 => easing_sine((end - time) / fade_time),
+                _ => 1.0,
+            };
+            let (motion_in, motion_out) = (
+                fade_in(motion.fade_in_time()),
+                fade_out(motion.fade_out_time()),
+            );
+            motion.apply_with(model, offset, |curve| {
+                let curve_in = curve.fade_in_time.map_or(motion_in, &fade_in);
+                let curve_out = curve.fade_out_time.map_or(motion_out, &fade_out);
+                curve_in * curve_out
+            });
+        }
+        self.entries.retain(|entry| match entry.end_time {
+            Some(end) => end >= time,
+            None => true,
+        });
+        if self.entries.is_empty() {
+            self.current_priority = None;
+        }
+        updated
+    }
+}
+
+pub(crate) fn easing_sine(value: f32) -> f32 {
+    let value = value.clamp(0.0, 1.0);
+    0.5 - 0.5 * (value * PI).cos()
+}
+
+#[cfg(all(test, feature = "mock-core"))]
+mod tests {
+    use super::{MotionManager, MotionPriority};
+    use anim::{Curve, CurveTarget, Motion, MotionEvent, MotionMeta};
+    use core::mock::MockMoc;
+    use mdl::Model;
+    use std::sync::Arc;
+
+    fn motion(
+        value: f32,
+        duration: f32,
+        looping: bool,
+        fade_in: f32,
+        fade_out: f32,
+    ) -> Arc<Motion> {
+        Arc::new(Motion {
+            meta: MotionMeta {
+                duration,
+                fps: 30.0,
+                looping,
+                are_beziers_restricted: false,
+                fade_in_time: Some(fade_in),
+                fade_out_time: Some(fade_out),
+            },
+            curves: vec![Curve {
+                target: CurveTarget::Parameter,
+                id: "ParamAngleX".to_owned(),
+                fade_in_time: None,
+                fade_out_time: None,
+                start: (0.0, value),
+                segments: Vec::new(),
+            }],
+            events: Vec::new(),
+            loop_fade_in: true,
+        })
+    }
+
+    fn update(manager: &mut MotionManager, model: &mut Model, delta: f32) -> f32 {
+        model.set_parameter_value("ParamAngleX", 0.0);
+        manager.update(model, delta);
+        model.parameter_value("ParamAngleX")
+    }
+
+    fn assert_near(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-4, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn priorities() {
+        let mut manager = MotionManager::new();
+        let idle = motion(1.0, 1.0, false, 0.0, 0.0);
+        assert!(manager.start(idle.clone(), MotionPriority::Idle).is_some());
+        assert_eq!(manager.current_priority(), Some(MotionPriority::Idle));
+        assert!(manager.start(idle.clone(), MotionPriority::Idle).is_none());
+
+        assert!(manager.reserve(MotionPriority::Normal));
+        assert!(!manager.reserve(MotionPriority::Normal));
+        assert!(manager
+            .start(idle.clone(), MotionPriority::Normal)
+            .is_some());
+        assert_eq!(manager.reserve_priority(), None);
+        assert!(manager
+            .start(idle.clone(), MotionPriority::Normal)
+            .is_none());
+        assert!(manager.start(idle.clone(), MotionPriority::Force).is_some());
+        assert!(manager.start(idle.clone(), MotionPriority::Force).is_some());
+
+        manager.stop_all();
+        assert!(manager.is_finished());
+        assert_eq!(manager.current_priority(), None);
+        assert!(manager.start(idle, MotionPriority::Idle).is_some());
+    }
+
+    #[test]
+    fn crossfade() {
+        let mut model = Model::from_bytes(&MockMoc::sample().to_bytes()).unwrap();
+        let mut manager = MotionManager::new();
+        let a = manager
+            .start(motion(-30.0, 5.0, false, 0.0, 1.0), MotionPriority::Idle)
+            .unwrap();
+        assert_eq!(update(&mut manager, &mut model, 0.5), -30.0);
+
+        let b = manager
+            .start(motion(30.0, 5.0, false, 1.0, 1.0), MotionPriority::Normal)
+            .unwrap();
+        // a is halfway faded out while b only starts fading in
+        assert_near(update(&mut manager, &mut model, 0.5), -15.0);
+        assert!(manager.is_playing(a));
+        assert_near(update(&mut manager, &mut model, 0.5), 15.0);
+        assert_near(update(&mut manager, &mut model, 0.5), 30.0);
+        assert!(!manager.is_playing(a));
+
+        // b finishes after its duration and fades out during its last second
+        assert_near(update(&mut manager, &mut model, 3.5), 15.0);
+        assert!(manager.is_playing(b));
+        update(&mut manager, &mut model, 0.6);
+        assert!(manager.is_finished());
+        assert_eq!(manager.current_priority(), None);
+    }
+
+    #[test]
+    fn looping() {
+        let mut model = Model::from_bytes(&MockMoc::sample().to_bytes()).unwrap();
+        let mut manager = MotionManager::new();
+        let looped = motion(30.0, 1.0, true, 0.5, 1.0);
+        let handle = manager.start(looped.clone(), MotionPriority::Idle).unwrap();
+        // the first frame is applied with the fade in just starting
+        assert_eq!(update(&mut manager, &mut model, 0.1), 0.0);
+        assert_near(update(&mut manager, &mut model, 0.25), 15.0);
+        assert_near(update(&mut manager, &mut model, 0.5), 30.0);
+        // the fade in restarts with every loop
+        assert_near(update(&mut manager, &mut model, 0.5), 15.0);
+        assert!(manager.is_playing(handle));
+
+        let mut no_fade = (*looped).clone();
+        no_fade.loop_fade_in = false;
+        manager.stop_all();
+        let handle = manager
+            .start(Arc::new(no_fade), MotionPriority::Idle)
+            .unwrap();
+        update(&mut manager, &mut model, 1.0);
+        assert_near(update(&mut manager, &mut model, 1.5), 30.0);
+
+        // looping motions only end when they are stopped
+        manager.stop(handle);
+        assert_eq!(update(&mut manager, &mut model, 10.0), 0.0);
+        assert!(manager.is_finished());
+    }
+
+    fn fired(manager: &MotionManager) -> Vec<&str> {
+        manager
+            .events()
+            .iter()
+            .map(|event| event.value.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn events() {
+        let mut model = Model::from_bytes(&MockMoc::sample().to_bytes()).unwrap();
+        let mut manager = MotionManager::new();
+        let mut with_events = (*motion(0.0, 1.0, true, 0.0, 0.0)).clone();
+        with_events.events = vec![
+            MotionEvent {
+                time: 0.0,
+                value: "start".to_owned(),
+            },
+            MotionEvent {
+                time: 0.4,
+                value: "middle".to_owned(),
+            },
+            MotionEvent {
+                time: 1.0,
+                value: "end".to_owned(),
+            },
+        ];
+        let with_events = Arc::new(with_events);
+        let handle = manager
+            .start(with_events.clone(), MotionPriority::Idle)
+            .unwrap();
+        manager.update(&mut model, 0.1);
+        assert!(fired(&manager).is_empty());
+        manager.update(&mut model, 0.25);
+        assert_eq!(fired(&manager), &["start"]);
+        assert_eq!(manager.events()[0].handle, handle);
+        manager.update(&mut model, 0.25);
+        assert_eq!(fired(&manager), &["middle"]);
+        manager.update(&mut model, 0.25);
+        assert!(fired(&manager).is_empty());
+        // the end of a loop and the start of the next one
+        manager.update(&mut model, 0.5);
+        assert_eq!(fired(&manager), &["end", "start"]);
+        // skipping whole loops fires every event once
+        manager.update(&mut model, 2.0);
+        assert_eq!(fired(&manager), &["middle", "end", "start"]);
+
+        // seeking skips events
+        manager.seek(handle, 0.75);
+        manager.update(&mut model, 0.0);
+        assert!(fired(&manager).is_empty());
+        manager.seek(handle, 0.4);
+        manager.update(&mut model, 0.1);
+        assert_eq!(fired(&manager), &["middle"]);
+        // seeking past the end of a looping motion wraps around without replaying the skipped loops
+        manager.seek(handle, 10.25);
+        manager.update(&mut model, 0.0);
+        assert!(fired(&manager).is_empty());
+        manager.update(&mut model, 0.25);
+        assert_eq!(fired(&manager), &["middle"]);
+
+        // events of motions that don't loop fire once
+        let mut once = (*with_events).clone();
+        once.meta.looping = false;
+        manager.stop_all();
+        manager.start(Arc::new(once), MotionPriority::Force);
+        manager.update(&mut model, 0.1);
+        manager.update(&mut model, 5.0);
+        assert_eq!(fired(&manager), &["start", "middle", "end"]);
+        assert!(manager.is_finished());
+
+        // a huge delta doesn't replay the events of every skipped loop
+        manager.start(with_events, MotionPriority::Idle);
+        manager.update(&mut model, 0.1);
+        manager.update(&mut model, 1_000_000.0);
+        assert_eq!(fired(&manager), &["start", "middle", "end"]);
+    }
+
+    #[test]
+    fn curve_fades() {
+        let mut model = Model::from_bytes(&MockMoc::sample().to_bytes()).unwrap();
+        let mut manager = MotionManager::new();
+        let mut curve_fade = (*motion(30.0, 5.0, false, 1.0, 1.0)).clone();
+        curve_fade.curves[0].fade_in_time = Some(0.0);
+        manager.start(Arc::new(curve_fade), MotionPriority::Idle);
+        assert_eq!(update(&mut manager, &mut model, 0.1), 30.0);
+    }
+}
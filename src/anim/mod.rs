@@ -0,0 +1,9 @@
+mod curve;
+mod expression;
+mod manager;
+mod motion;
+
+pub use self::curve::{Curve, CurveTarget, Segment};
+pub use self::expression::{Expression, ExpressionBlend, ExpressionManager, ExpressionParameter};
+pub use self::manager::{FiredEvent, MotionHandle, MotionManager, MotionPriority};
+pub use self::motion::{Motion, MotionEvent, MotionMeta};
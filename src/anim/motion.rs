@@ -0,0 +1,337 @@
+//! The motion3.json animation file
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use serde_json;
+
+use super::curve::{Curve, CurveTarget};
+use mdl::Model;
+use {CubismError, Result};
+
+/// The metadata of a [Motion](./struct.Motion.html).
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct MotionMeta {
+    /// The duration of the motion in seconds.
+    pub duration: f32,
+    /// The frame rate the motion was authored with.
+    pub fps: f32,
+    /// Whether the motion should be looped.
+    #[serde(rename = "Loop", default)]
+    pub looping: bool,
+    /// Whether the time of bezier segments is interpolated linearly instead of solving the curve for it.
+    #[serde(default)]
+    pub are_beziers_restricted: bool,
+    /// The fade in time of the motion in seconds.
+    #[serde(default)]
+    pub fade_in_time: Option<f32>,
+    /// The fade out time of the motion in seconds.
+    #[serde(default)]
+    pub fade_out_time: Option<f32>,
+}
+
+/// A timed event of a motion, from the `UserData` track of its motion3.json file.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct MotionEvent {
+    /// The time of the event in seconds.
+    pub time: f32,
+    /// The contents of the event.
+    pub value: String,
+}
+
+/// An animation loaded from a motion3.json file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Motion {
+    /// The metadata of this motion.
+    pub meta: MotionMeta,
+    /// The curves of this motion.
+    pub curves: Vec<Curve>,
+    /// The events of this motion, sorted by time.
+    pub events: Vec<MotionEvent>,
+    /// Whether a looping motion fades in again every time it restarts, true by default.
+    pub loop_fade_in: bool,
+}
+
+impl Motion {
+    /// The id of the model curve that animates the opacity of the whole model.
+    pub const OPACITY_CURVE: &'static str = "Opacity";
+
+    /// Loads the motion3.json file at `path`.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_reader(&mut File::open(path)?)
+    }
+
+    /// Parses a motion from a reader instance.
+    pub fn from_reader<R: Read>(reader: &mut R) -> Result<Self> {
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf)?;
+        Self::from_slice(&buf)
+    }
+
+    /// Parses a motion from json data.
+    pub fn from_slice(data: &[u8]) -> Result<Self> {
+        let json: Motion3Json = serde_json::from_slice(data)?;
+        if json.version != 3 {
+            return Err(invalid(format!(
+                "unsupported motion3.json version {}",
+                json.version
+            )));
+        }
+        let meta = json.meta;
+        if meta.duration < 0.0 || meta.duration.is_nan() {
+            return Err(invalid(format!("invalid duration {}", meta.duration)));
+        }
+        check_fades(meta.fade_in_time, meta.fade_out_time, "the motion")?;
+        let curves = json
+            .curves
+            .into_iter()
+            .map(|curve| {
+                check_fades(curve.fade_in_time, curve.fade_out_time, &curve.id)?;
+                let (start, segments) = Curve::parse_segments(&curve.segments)
+                    .ok_or_else(|| invalid(format!("malformed segments in curve {}", curve.id)))?;
+                Ok(Curve {
+                    target: curve.target,
+                    id: curve.id,
+                    fade_in_time: curve.fade_in_time,
+                    fade_out_time: curve.fade_out_time,
+                    start,
+                    segments,
+                })
+            })
+            .collect::<Result<_>>()?;
+        let mut events = json.user_data;
+        if let Some(event) = events.iter().find(|event| event.time.is_nan()) {
+            return Err(invalid(format!("invalid time for event {}", event.value)));
+        }
+        events.sort_by(|a, b| a.time.partial_cmp(&b.time).unwrap());
+        Ok(Motion {
+            meta,
+            curves,
+            events,
+            loop_fade_in: true,
+        })
+    }
+
+    /// Returns the fade in time of this motion in seconds, 1 if the file doesn't specify it.
+    #[inline]
+    pub fn fade_in_time(&self) -> f32 {
+        self.meta.fade_in_time.unwrap_or(1.0)
+    }
+
+    /// Returns the fade out time of this motion in seconds, 1 if the file doesn't specify it.
+    #[inline]
+    pub fn fade_out_time(&self) -> f32 {
+        self.meta.fade_out_time.unwrap_or(1.0)
+    }
+
+    /// Returns the events with a time in `from..to`.
+    pub fn events_between(&self, from: f32, to: f32) -> &[MotionEvent] {
+        let start = self.events.partition_point(|event| event.time < from);
+        let end = self.events.partition_point(|event| event.time < to);
+        &self.events[start..end.max(start)]
+    }
+
+    /// Returns the curve animating `id` of the specified target.
+    pub fn curve(&self, target: CurveTarget, id: &str) -> Option<&Curve> {
+        self.curves
+            .iter()
+            .find(|curve| curve.target == target && curve.id == id)
+    }
+
+    /// Returns the value of the curve animating `id` of the specified target at `time`.
+    pub fn curve_value(&self, target: CurveTarget, id: &str, time: f32) -> Option<f32> {
+        self.curve(target, id)
+            .map(|curve| curve.evaluate(time, self.meta.are_beziers_restricted))
+    }
+
+    /// Returns the opacity of the whole model at `time`, 1 if this motion doesn't animate it.
+    pub fn opacity(&self, time: f32) -> f32 {
+        self.curve_value(CurveTarget::Model, Self::OPACITY_CURVE, time)
+            .unwrap_or(1.0)
+    }
+
+    /// Evaluates all parameter and part opacity curves at `time` and writes the results into `model`.
+    ///
+    /// Parameter values are blended towards the curves by `weight`. Like in the official framework the
+    /// values of part opacity curves are written unweighted into the parameter with the id of the part,
+    /// a virtual one if the moc has none, and a [Pose](./struct.Pose.html) fades the parts from
+    /// there, see [virtual_parameter_value](./struct.Model.html#method.virtual_parameter_value).
+    /// Curves of parameters and parts the model lacks are skipped, model curves are available through
+    /// [curve_value](#method.curve_value).
+    pub fn apply(&self, model: &mut Model, time: f32, weight: f32) {
+        self.apply_with(model, time, |_| weight)
+    }
+
+    /// Like `apply`, with a weight for every curve.
+    pub(crate) fn apply_with<F>(&self, model: &mut Model, time: f32, weight: F)
+    where
+        F: Fn(&Curve) -> f32,
+    {
+        for curve in &self.curves {
+            let value = curve.evaluate(time, self.meta.are_beziers_restricted);
+            match curve.target {
+                CurveTarget::Parameter => {
+                    if let Some(mut param) = model.parameter_mut(&curve.id) {
+                        param.set_value_weighted(value, weight(curve));
+                    }
+                }
+                CurveTarget::PartOpacity => {
+                    if model.part_index(&curve.id).is_some() {
+                        model.set_virtual_parameter_value(&curve.id, value);
+                    }
+                }
+                CurveTarget::Model => (),
+            }
+        }
+    }
+}
+
+fn check_fades(fade_in: Option<f32>, fade_out: Option<f32>, what: &str) -> Result<()> {
+    for fade in fade_in.into_iter().chain(fade_out) {
+        if fade < 0.0 || fade.is_nan() {
+            return Err(invalid(format!("invalid fade time {} for {}", fade, what)));
+        }
+    }
+    Ok(())
+}
+
+fn invalid(msg: String) -> CubismError {
+    CubismError::InvalidJson(msg)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Motion3Json {
+    version: u32,
+    meta: MotionMeta,
+    curves: Vec<Curve3Json>,
+    #[serde(default)]
+    user_data: Vec<MotionEvent>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Curve3Json {
+    target: CurveTarget,
+    id: String,
+    #[serde(default)]
+    fade_in_time: Option<f32>,
+    #[serde(default)]
+    fade_out_time: Option<f32>,
+    segments: Vec<f32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Motion;
+    use anim::{CurveTarget, Segment};
+    use CubismError;
+
+    const MOTION3: &str = r#"{
+        "Version": 3,
+        "Meta": {
+            "Duration": 2.0,
+            "Fps": 30.0,
+            "Loop": true,
+            "AreBeziersRestricted": true,
+            "CurveCount": 4,
+            "TotalSegmentCount": 4,
+            "TotalPointCount": 6,
+            "UserDataCount": 2,
+            "TotalUserDataSize": 10,
+            "FadeInTime": 0.5
+        },
+        "Curves": [
+            {"Target": "Model", "Id": "Opacity", "Segments": [0, 1, 0, 2, 0.5]},
+            {"Target": "Parameter", "Id": "ParamAngleX", "FadeOutTime": 0.25,
+                "Segments": [0, -30, 1, 0.5, -30, 1.5, 30, 2, 30]},
+            {"Target": "Parameter", "Id": "ParamMissing", "Segments": [0, 1]},
+            {"Target": "PartOpacity", "Id": "PartArm", "Segments": [0, 1, 2, 1, 0, 3, 2, 0]}
+        ],
+        "UserData": [
+            {"Time": 1.5, "Value": "step"},
+            {"Time": 0.0, "Value": "start"}
+        ]
+    }"#;
+
+    #[test]
+    fn motion_from_json() {
+        let motion = Motion::from_slice(MOTION3.as_bytes()).unwrap();
+        assert_eq!(motion.meta.duration, 2.0);
+        assert!(motion.meta.looping);
+        assert!(motion.meta.are_beziers_restricted);
+        assert_eq!(motion.meta.fade_in_time, Some(0.5));
+        assert_eq!(motion.meta.fade_out_time, None);
+        assert_eq!(motion.curves.len(), 4);
+        let angle = motion.curve(CurveTarget::Parameter, "ParamAngleX").unwrap();
+        assert_eq!(angle.fade_out_time, Some(0.25));
+        assert_eq!(
+            angle.segments,
+            &[Segment::Bezier((0.5, -30.0), (1.5, 30.0), (2.0, 30.0))]
+        );
+        assert_eq!(motion.opacity(1.0), 0.75);
+        assert_eq!(
+            motion.curve_value(CurveTarget::Parameter, "ParamAngleX", 1.0),
+            Some(0.0)
+        );
+        assert_eq!(
+            motion.curve_value(CurveTarget::PartOpacity, "ParamAngleX", 1.0),
+            None
+        );
+
+        assert_eq!(motion.events[0].value, "start");
+        assert_eq!(motion.events[1].time, 1.5);
+        assert_eq!(motion.events_between(0.0, 1.5).len(), 1);
+        assert_eq!(motion.events_between(0.5, 2.0)[0].value, "step");
+        assert!(motion.events_between(2.0, 0.0).is_empty());
+    }
+
+    #[test]
+    fn invalid_motions() {
+        let invalid = [
+            MOTION3.replace(r#""Version": 3"#, r#""Version": 2"#),
+            MOTION3.replace(r#""Duration": 2.0"#, r#""Duration": -1.0"#),
+            MOTION3.replace(r#""FadeOutTime": 0.25"#, r#""FadeOutTime": -0.25"#),
+            MOTION3.replace("[0, 1, 0, 2, 0.5]", "[0, 1, 0, 2]"),
+            MOTION3.replace("[0, 1, 0, 2, 0.5]", "[0, 1, 7, 2, 0.5]"),
+        ];
+        for json in &invalid {
+            match Motion::from_slice(json.as_bytes()) {
+                Err(CubismError::InvalidJson(_)) => (),
+                other => panic!("unexpected result {:?}", other),
+            }
+        }
+        match Motion::from_slice(br#"{"Version": 3}"#) {
+            Err(CubismError::Json(_)) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[cfg(feature = "mock-core")]
+    #[test]
+    fn apply_motion() {
+        use core::mock::MockMoc;
+        use mdl::Model;
+
+        let motion = Motion::from_slice(MOTION3.as_bytes()).unwrap();
+        let mut model = Model::from_bytes(&MockMoc::sample().to_bytes()).unwrap();
+        let part = |model: &Model| model.virtual_parameter_value("PartArm");
+        motion.apply(&mut model, 0.0, 1.0);
+        assert_eq!(model.parameter_value("ParamAngleX"), -30.0);
+        assert_eq!(part(&model), Some(1.0));
+
+        // part opacity curves ignore the weight and leave the opacity to the pose
+        model.set_part_opacity("PartArm", 0.5);
+        motion.apply(&mut model, 1.0, 0.5);
+        assert_eq!(model.parameter_value("ParamAngleX"), -15.0);
+        assert_eq!(part(&model), Some(0.0));
+        assert_eq!(model.part_opacity("PartArm"), 0.5);
+
+        // past the end the last values are held
+        motion.apply(&mut model, 5.0, 1.0);
+        assert_eq!(model.parameter_value("ParamAngleX"), 30.0);
+        assert_eq!(part(&model), Some(0.0));
+    }
+}
@@ -0,0 +1,300 @@
+//! Loading a model with all of its assets
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{BufReader, Read};
+use std::path::Path;
+use std::sync::Arc;
+
+use png;
+
+use anim::{Expression, Motion};
+use mdl::{Moc, Model};
+use physics::Physics;
+use pose::Pose;
+use settings::ModelSettings;
+use {CubismError, Result};
+
+/// A decoded texture in 8 bit per channel RGBA format with straight alpha.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Texture {
+    /// The width of the texture in pixels.
+    pub width: u32,
+    /// The height of the texture in pixels.
+    pub height: u32,
+    /// The pixel data, row by row from the top.
+    pub data: Vec<u8>,
+}
+
+impl Texture {
+    /// Decodes a png image from a reader instance.
+    pub fn from_reader<R: Read>(reader: R) -> Result<Self> {
+        let mut decoder = png::Decoder::new(reader);
+        decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+        let mut reader = decoder.read_info()?;
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf)?;
+        buf.truncate(info.buffer_size());
+        let data = match info.color_type {
+            png::ColorType::Rgba => buf,
+            png::ColorType::Rgb => buf
+                .chunks(3)
+                .flat_map(|rgb| vec![rgb[0], rgb[1], rgb[2], 0xFF])
+                .collect(),
+            png::ColorType::GrayscaleAlpha => buf
+                .chunks(2)
+                .flat_map(|ga| vec![ga[0], ga[0], ga[0], ga[1]])
+                .collect(),
+            png::ColorType::Grayscale => buf.iter().flat_map(|&g| vec![g, g, g, 0xFF]).collect(),
+            png::ColorType::Indexed => {
+                return Err("indexed png was not expanded by the decoder".into());
+            }
+        };
+        Ok(Texture {
+            width: info.width,
+            height: info.height,
+            data,
+        })
+    }
+
+    /// Decodes the png image at `path`.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_reader(BufReader::new(File::open(path)?))
+    }
+}
+
+/// A model together with everything its model3.json references, loaded with [ModelBundle::load](#method.load).
+#[derive(Debug)]
+pub struct ModelBundle {
+    /// The parsed model3.json file.
+    pub settings: ModelSettings,
+    /// The moc of the model.
+    pub moc: Arc<Moc>,
+    /// A model instance created from the moc.
+    pub model: Model,
+    /// The decoded textures, indexed by the texture indices of the drawables.
+    pub textures: Vec<Texture>,
+    /// The physics of the model.
+    pub physics: Option<Physics>,
+    /// The pose of the model.
+    pub pose: Option<Pose>,
+    /// The expressions by name.
+    pub expressions: BTreeMap<String, Arc<Expression>>,
+    /// The motions by group name, in the order of the model3.json file.
+    ///
+    /// Fade times given in the model3.json file replace the ones of the motion files.
+    pub motions: BTreeMap<String, Vec<Arc<Motion>>>,
+}
+
+impl ModelBundle {
+    /// Loads the model3.json file at `path` and every file it references.
+    ///
+    /// Errors that occur while loading a file are wrapped in [CubismError::File](enum.CubismError.html#variant.File)
+    /// together with the path of the offending file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let settings = in_file(path, ModelSettings::from_path(path))?;
+        let moc = in_file(
+            &settings.moc,
+            fs::read(&settings.moc)
+                .map_err(CubismError::from)
+                .and_then(|data| Moc::from_bytes(&data)),
+        )?;
+        let model = Model::new(&moc)?;
+        let textures = settings
+            .textures
+            .iter()
+            .map(|path| in_file(path, Texture::from_path(path)))
+            .collect::<Result<_>>()?;
+        let physics = match settings.physics {
+            Some(ref path) => Some(in_file(path, Physics::from_path(path))?),
+            None => None,
+        };
+        let pose = match settings.pose {
+            Some(ref path) => Some(in_file(path, Pose::from_path(path))?),
+            None => None,
+        };
+        let expressions = settings
+            .expressions
+            .iter()
+            .map(|exp| {
+                let expression = in_file(&exp.file, Expression::from_path(&exp.file))?;
+                Ok((exp.name.clone(), Arc::new(expression)))
+            })
+            .collect::<Result<_>>()?;
+        let motions = settings
+            .motions
+            .iter()
+            .map(|(group, motions)| {
+                let motions = motions
+                    .iter()
+                    .map(|reference| {
+                        let mut motion =
+                            in_file(&reference.file, Motion::from_path(&reference.file))?;
+                        if reference.fade_in_time.is_some() {
+                            motion.meta.fade_in_time = reference.fade_in_time;
+                        }
+                        if reference.fade_out_time.is_some() {
+                            motion.meta.fade_out_time = reference.fade_out_time;
+                        }
+                        Ok(Arc::new(motion))
+                    })
+                    .collect::<Result<_>>()?;
+                Ok((group.clone(), motions))
+            })
+            .collect::<Result<_>>()?;
+        Ok(ModelBundle {
+            settings,
+            moc,
+            model,
+            textures,
+            physics,
+            pose,
+            expressions,
+            motions,
+        })
+    }
+}
+
+fn in_file<T>(path: &Path, result: Result<T>) -> Result<T> {
+    result.map_err(|err| CubismError::File(path.to_owned(), Box::new(err)))
+}
+
+#[cfg(all(test, feature = "mock-core"))]
+mod tests {
+    use super::{ModelBundle, Texture};
+    use core::mock::MockMoc;
+    use png;
+    use std::fs;
+    use std::io;
+    use std::path::PathBuf;
+    use CubismError;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = ::std::env::temp_dir().join(format!("cubism-{}-{}", name, ::std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("motions")).unwrap();
+        dir
+    }
+
+    fn write_png(path: &PathBuf, color: png::ColorType, width: u32, data: &[u8]) {
+        let file = fs::File::create(path).unwrap();
+        let height = data.len() as u32 / width / color.samples() as u32;
+        let mut encoder = png::Encoder::new(file, width, height);
+        encoder.set_color(color);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .write_header()
+            .unwrap()
+            .write_image_data(data)
+            .unwrap();
+    }
+
+    #[test]
+    fn load_bundle() {
+        let dir = temp_dir("bundle");
+        fs::write(
+            dir.join("model.model3.json"),
+            r#"{
+                "Version": 3,
+                "FileReferences": {
+                    "Moc": "model.moc3",
+                    "Textures": ["texture_00.png", "texture_01.png"],
+                    "Physics": "model.physics3.json",
+                    "Pose": "model.pose3.json",
+                    "Expressions": [{"Name": "smile", "File": "smile.exp3.json"}],
+                    "Motions": {"Idle": [{"File": "motions/idle.motion3.json", "FadeInTime": 0.5}]}
+                }
+            }"#,
+        )
+        .unwrap();
+        fs::write(dir.join("model.moc3"), MockMoc::sample().to_bytes()).unwrap();
+        write_png(
+            &dir.join("texture_00.png"),
+            png::ColorType::Rgb,
+            2,
+            &[255, 0, 0, 0, 255, 0],
+        );
+        write_png(
+            &dir.join("texture_01.png"),
+            png::ColorType::Rgba,
+            1,
+            &[1, 2, 3, 4],
+        );
+        fs::write(
+            dir.join("model.physics3.json"),
+            r#"{
+                "Version": 3,
+                "Meta": {},
+                "PhysicsSettings": [{
+                    "Id": "PhysicsSetting1",
+                    "Vertices": [{"Mobility": 1, "Delay": 1, "Acceleration": 1, "Radius": 0}],
+                    "Normalization": {
+                        "Position": {"Minimum": -10, "Default": 0, "Maximum": 10},
+                        "Angle": {"Minimum": -10, "Default": 0, "Maximum": 10}
+                    }
+                }]
+            }"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.join("model.pose3.json"),
+            r#"{"Type": "Live2D Pose", "Groups": [[{"Id": "PartArm", "Link": []}]]}"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.join("smile.exp3.json"),
+            r#"{"Type": "Live2D Expression"}"#,
+        )
+        .unwrap();
+        fs::write(dir.join("motions/idle.motion3.json"), r#"{
+                "Version": 3,
+                "Meta": {"Duration": 1.0, "Fps": 30.0},
+                "Curves": [{"Target": "Parameter", "Id": "ParamAngleX", "Segments": [0, 0, 0, 1, 30]}]
+            }"#).unwrap();
+
+        let bundle = ModelBundle::load(dir.join("model.model3.json")).unwrap();
+        assert_eq!(bundle.model.parameter_ids(), bundle.moc.parameter_ids());
+        assert_eq!(
+            bundle.textures[0],
+            Texture {
+                width: 2,
+                height: 1,
+                data: vec![255, 0, 0, 255, 0, 255, 0, 255],
+            }
+        );
+        assert_eq!(bundle.textures[1].data, &[1, 2, 3, 4]);
+        assert_eq!(bundle.physics.as_ref().unwrap().settings().len(), 1);
+        assert_eq!(bundle.pose.as_ref().unwrap().groups[0][0].id, "PartArm");
+        assert_eq!(bundle.expressions["smile"].fade_in_time, 1.0);
+        assert_eq!(bundle.motions["Idle"][0].meta.duration, 1.0);
+        assert_eq!(bundle.motions["Idle"][0].fade_in_time(), 0.5);
+        assert_eq!(bundle.motions["Idle"][0].fade_out_time(), 1.0);
+
+        // a missing file
+        fs::remove_file(dir.join("motions/idle.motion3.json")).unwrap();
+        match ModelBundle::load(dir.join("model.model3.json")) {
+            Err(CubismError::File(path, err)) => {
+                assert_eq!(path, dir.join("motions/idle.motion3.json"));
+                match *err {
+                    CubismError::Io(ref err) => assert_eq!(err.kind(), io::ErrorKind::NotFound),
+                    ref other => panic!("unexpected error {:?}", other),
+                }
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        // a malformed file
+        fs::write(dir.join("model.moc3"), b"not a moc").unwrap();
+        match ModelBundle::load(dir.join("model.model3.json")) {
+            Err(CubismError::File(path, err)) => {
+                assert_eq!(path, dir.join("model.moc3"));
+                match *err {
+                    CubismError::InvalidMoc => (),
+                    ref other => panic!("unexpected error {:?}", other),
+                }
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
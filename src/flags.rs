@@ -1,9 +1,9 @@
 use libc::c_uchar;
 
 use core::{
-    csmBlendAdditive, csmBlendMultiplicative, csmDrawOrderDidChange, csmIsDoubleSided,
-    csmIsVisible, csmOpacityDidChange, csmRenderOrderDidChange, csmVertexPositionsDidChange,
-    csmVisibilityDidChange,
+    csmBlendAdditive, csmBlendColorDidChange, csmBlendMultiplicative, csmDrawOrderDidChange,
+    csmIsDoubleSided, csmIsVisible, csmOpacityDidChange, csmRenderOrderDidChange,
+    csmVertexPositionsDidChange, csmVisibilityDidChange,
 };
 
 bitflags! {
@@ -35,5 +35,15 @@ bitflags! {
         const RENDER_ORDER_CHANGED = csmRenderOrderDidChange;
         /// The drawable's vertex positions changed since the last update.
         const VERTEX_POSITIONS_CHANGED = csmVertexPositionsDidChange;
+        /// The drawable's multiply or screen color changed since the last update.
+        const BLEND_COLOR_CHANGED = csmBlendColorDidChange;
+    }
+}
+
+impl DynamicFlags {
+    /// Returns only the flags that describe a change, that is everything except `IS_VISIBLE`.
+    #[inline]
+    pub fn changes(self) -> Self {
+        self - DynamicFlags::IS_VISIBLE
     }
 }
@@ -5,17 +5,47 @@ extern crate cubism_core_sys as core;
 extern crate libc;
 #[macro_use]
 extern crate bitflags;
+#[macro_use]
+extern crate log;
+extern crate png;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
 
+use std::path::PathBuf;
 use std::{error, fmt, io, str};
 
+mod anim;
+mod bundle;
 mod flags;
+mod logging;
 mod mdl;
 mod mem;
+mod physics;
+mod pose;
+mod settings;
 
+pub use anim::*;
+pub use bundle::*;
 pub use flags::*;
+pub use logging::*;
 pub use mdl::*;
+pub use physics::*;
+pub use pose::*;
+pub use settings::*;
+
+/// Loads the Live2DCubismCore shared library at `path`, this has to be done once before any moc is loaded.
+///
+/// Only available with the `dynamic` feature, the library stays loaded for the rest of the program.
+#[cfg(feature = "dynamic")]
+pub fn load_core<P: AsRef<::std::ffi::OsStr>>(path: P) -> Result<()> {
+    core::load_core(path).map_err(CubismError::CoreLibrary)
+}
 
 /// Returns the linked library version in a (version, major, minor, patch) tuple
+///
+/// With the `dynamic` feature this panics if the core hasn't been loaded yet.
 pub fn version() -> (u32, u32, u32, u32) {
     let version = unsafe { core::csmGetVersion() };
     let major = (version & 0xFF00_0000) >> 24;
@@ -33,6 +63,30 @@ pub enum CubismError {
     InvalidId(str::Utf8Error),
     /// An I/O error occured.
     Io(io::Error),
+    /// The data passed in is not a moc or has been rejected by the core.
+    InvalidMoc,
+    /// The moc was created with a newer version of the file format than the core supports.
+    UnsupportedMocVersion {
+        /// The version of the moc.
+        version: u32,
+        /// The latest version supported by the core.
+        latest: u32,
+    },
+    /// The moc failed the core's consistency check, it is most likely truncated or corrupted.
+    InconsistentMoc,
+    /// The core library could not be loaded, only returned with the `dynamic` feature.
+    CoreLibrary(core::LoadError),
+    /// A moc was loaded before the core library has been loaded with [load_core](fn.load_core.html),
+    /// only returned with the `dynamic` feature.
+    CoreNotLoaded,
+    /// A json file could not be parsed.
+    Json(serde_json::Error),
+    /// A json file was parsed but its contents are invalid, contains a description of the problem.
+    InvalidJson(String),
+    /// A png texture could not be decoded.
+    InvalidTexture(png::DecodingError),
+    /// An error occured while loading the file at the contained path.
+    File(PathBuf, Box<CubismError>),
     /// A different error
     Other(String),
 }
@@ -42,9 +96,29 @@ impl error::Error for CubismError {
         match *self {
             CubismError::InvalidId(ref err) => err.description(),
             CubismError::Io(ref err) => err.description(),
+            CubismError::InvalidMoc => "invalid moc data",
+            CubismError::UnsupportedMocVersion { .. } => "unsupported moc version",
+            CubismError::InconsistentMoc => "moc consistency check failed",
+            CubismError::CoreLibrary(_) => "failed to load the cubism core",
+            CubismError::CoreNotLoaded => "the cubism core has not been loaded",
+            CubismError::Json(_) => "invalid json",
+            CubismError::InvalidJson(_) => "invalid json contents",
+            CubismError::InvalidTexture(_) => "invalid png texture",
+            CubismError::File(..) => "error while loading a file",
             CubismError::Other(ref s) => s,
         }
     }
+
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            CubismError::Io(ref err) => Some(err),
+            CubismError::CoreLibrary(ref err) => Some(err),
+            CubismError::Json(ref err) => Some(err),
+            CubismError::InvalidTexture(ref err) => Some(err),
+            CubismError::File(_, ref err) => Some(&**err),
+            _ => None,
+        }
+    }
 }
 
 impl fmt::Display for CubismError {
@@ -52,6 +126,19 @@ impl fmt::Display for CubismError {
         match *self {
             CubismError::InvalidId(ref err) => err.fmt(fmt),
             CubismError::Io(ref err) => err.fmt(fmt),
+            CubismError::InvalidMoc => fmt.write_str("invalid moc data"),
+            CubismError::UnsupportedMocVersion { version, latest } => write!(
+                fmt,
+                "unsupported moc version {}, the core supports versions up to {}",
+                version, latest
+            ),
+            CubismError::InconsistentMoc => fmt.write_str("moc consistency check failed"),
+            CubismError::CoreLibrary(ref err) => err.fmt(fmt),
+            CubismError::CoreNotLoaded => fmt.write_str("the cubism core has not been loaded"),
+            CubismError::Json(ref err) => err.fmt(fmt),
+            CubismError::InvalidJson(ref s) => fmt.write_str(s),
+            CubismError::InvalidTexture(ref err) => err.fmt(fmt),
+            CubismError::File(ref path, ref err) => write!(fmt, "{}: {}", path.display(), err),
             CubismError::Other(ref s) => fmt.write_str(s),
         }
     }
@@ -69,8 +156,43 @@ impl From<io::Error> for CubismError {
     }
 }
 
+impl From<serde_json::Error> for CubismError {
+    fn from(e: serde_json::Error) -> CubismError {
+        CubismError::Json(e)
+    }
+}
+
+impl From<png::DecodingError> for CubismError {
+    fn from(e: png::DecodingError) -> CubismError {
+        CubismError::InvalidTexture(e)
+    }
+}
+
 impl<'a> From<&'a str> for CubismError {
     fn from(e: &'a str) -> CubismError {
         CubismError::Other(e.to_owned())
     }
 }
+
+#[cfg(all(test, feature = "dynamic"))]
+mod tests {
+    use super::{load_core, CubismError, Moc};
+    use core::LoadError;
+    use std::error::Error;
+
+    #[test]
+    fn core_not_loaded() {
+        match load_core("/nonexistent/libLive2DCubismCore.so") {
+            Err(err @ CubismError::CoreLibrary(LoadError::Library(_))) => {
+                // the error of the library loader is kept in the chain
+                let source = err.source().unwrap();
+                assert!(source.source().is_some());
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+        match Moc::from_bytes(&[0; 64]) {
+            Err(CubismError::CoreNotLoaded) => (),
+            other => panic!("unexpected result {:?}", other.map(|_| ())),
+        }
+    }
+}
@@ -0,0 +1,130 @@
+//! Forwarding of the core's log messages.
+use libc::c_char;
+
+use std::ffi::CStr;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use core::{self, csmLogFunction};
+
+type Handler = Arc<dyn Fn(&str) + Send + Sync>;
+
+/// The closure messages are currently forwarded to, `None` forwards them to the `log` crate.
+static HANDLER: Mutex<Option<Handler>> = Mutex::new(None);
+
+fn handler() -> MutexGuard<'static, Option<Handler>> {
+    // the handler is only ever replaced as a whole, so a poisoned lock is still consistent
+    HANDLER.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+unsafe extern "C" fn trampoline(message: *const c_char) {
+    if message.is_null() {
+        return;
+    }
+    let message = CStr::from_ptr(message).to_string_lossy();
+    let message = message.trim_end();
+    // clone the handler out so that it may install a different handler itself
+    let handler = handler().clone();
+    // unwinding into the core is undefined behaviour
+    let _ = panic::catch_unwind(AssertUnwindSafe(|| match handler {
+        Some(handler) => handler(message),
+        None => log_message(message),
+    }));
+}
+
+/// Logs a core message, the core prefixes its messages with `[CSM] [E]` where `E` denotes the level.
+fn log_message(message: &str) {
+    let level = match message.trim_start_matches("[CSM] ").get(..3) {
+        Some("[E]") => log::Level::Error,
+        Some("[W]") => log::Level::Warn,
+        Some("[D]") => log::Level::Debug,
+        _ => log::Level::Info,
+    };
+    log!(target: "cubism_core", level, "{}", message);
+}
+
+/// Restores the previously installed log handler when dropped.
+///
+/// Guards should be dropped in the reverse order of their creation, otherwise a handler that has been
+/// replaced in the meantime will be restored.
+#[must_use = "the previous log handler is restored when the guard is dropped"]
+pub struct LogGuard {
+    previous_function: csmLogFunction,
+    previous_handler: Option<Handler>,
+}
+
+impl ::std::fmt::Debug for LogGuard {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.debug_struct("LogGuard")
+            .field("previous_function", &self.previous_function)
+            .field("previous_handler", &self.previous_handler.is_some())
+            .finish()
+    }
+}
+
+impl Drop for LogGuard {
+    fn drop(&mut self) {
+        let mut handler = handler();
+        *handler = self.previous_handler.take();
+        unsafe { core::csmSetLogFunction(self.previous_function) };
+    }
+}
+
+fn install(new_handler: Option<Handler>) -> LogGuard {
+    let mut handler = handler();
+    let previous_handler = ::std::mem::replace(&mut *handler, new_handler);
+    let previous_function = unsafe { core::csmGetLogFunction() };
+    unsafe { core::csmSetLogFunction(Some(trampoline)) };
+    LogGuard {
+        previous_function,
+        previous_handler,
+    }
+}
+
+/// Forwards the core's log messages to the [log](https://docs.rs/log) crate under the `cubism_core` target.
+///
+/// The level is derived from the message's prefix, errors are logged as errors, warnings as warnings etc.
+pub fn forward_core_logs() -> LogGuard {
+    install(None)
+}
+
+/// Forwards the core's log messages to `handler`.
+///
+/// Messages are passed without their trailing newline, panics inside of the handler are caught and ignored.
+pub fn set_core_log_handler<F>(handler: F) -> LogGuard
+where
+    F: Fn(&str) + Send + Sync + 'static,
+{
+    install(Some(Arc::new(handler)))
+}
+
+#[cfg(all(test, feature = "mock-core"))]
+mod tests {
+    use super::set_core_log_handler;
+    use core;
+    use std::sync::{Arc, Mutex};
+    use Model;
+
+    #[test]
+    fn log_handler() {
+        let messages = Arc::new(Mutex::new(Vec::new()));
+        let previous = unsafe { core::csmGetLogFunction() };
+        {
+            let messages = messages.clone();
+            let _guard =
+                set_core_log_handler(move |msg| messages.lock().unwrap().push(msg.to_owned()));
+            let mut bytes = core::mock::MockMoc::sample().to_bytes();
+            bytes.pop();
+            assert!(Model::from_bytes(&bytes).is_err());
+        }
+        assert!(messages
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|msg| msg.contains("HasMocConsistency")));
+        assert_eq!(
+            unsafe { core::csmGetLogFunction() }.map(|f| f as usize),
+            previous.map(|f| f as usize)
+        );
+    }
+}
@@ -0,0 +1,128 @@
+//! Bounding boxes and hit testing
+
+/// An axis-aligned bounding box in model units.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    /// The corner with the smallest coordinates.
+    pub min: (f32, f32),
+    /// The corner with the largest coordinates.
+    pub max: (f32, f32),
+}
+
+impl Aabb {
+    /// Returns the smallest box containing all `points` or `None` if there are no points.
+    pub fn from_points(points: &[(f32, f32)]) -> Option<Self> {
+        let (&first, rest) = points.split_first()?;
+        let mut aabb = Aabb {
+            min: first,
+            max: first,
+        };
+        for &(x, y) in rest {
+            aabb.min = (aabb.min.0.min(x), aabb.min.1.min(y));
+            aabb.max = (aabb.max.0.max(x), aabb.max.1.max(y));
+        }
+        Some(aabb)
+    }
+
+    /// Returns the smallest box containing both this box and `other`.
+    #[inline]
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb {
+            min: (self.min.0.min(other.min.0), self.min.1.min(other.min.1)),
+            max: (self.max.0.max(other.max.0), self.max.1.max(other.max.1)),
+        }
+    }
+
+    /// Returns the width of this box.
+    #[inline]
+    pub fn width(&self) -> f32 {
+        self.max.0 - self.min.0
+    }
+
+    /// Returns the height of this box.
+    #[inline]
+    pub fn height(&self) -> f32 {
+        self.max.1 - self.min.1
+    }
+
+    /// Returns the center of this box.
+    #[inline]
+    pub fn center(&self) -> (f32, f32) {
+        (
+            (self.min.0 + self.max.0) * 0.5,
+            (self.min.1 + self.max.1) * 0.5,
+        )
+    }
+
+    /// Returns true if `point` lies inside of this box or on its border.
+    #[inline]
+    pub fn contains(&self, (x, y): (f32, f32)) -> bool {
+        x >= self.min.0 && x <= self.max.0 && y >= self.min.1 && y <= self.max.1
+    }
+}
+
+/// Returns true if `point` lies inside of one of the triangles of the mesh or on its edges.
+pub(crate) fn mesh_contains(positions: &[(f32, f32)], indices: &[u16], point: (f32, f32)) -> bool {
+    indices.chunks(3).filter(|tri| tri.len() == 3).any(|tri| {
+        triangle_contains(
+            positions[tri[0] as usize],
+            positions[tri[1] as usize],
+            positions[tri[2] as usize],
+            point,
+        )
+    })
+}
+
+fn triangle_contains(a: (f32, f32), b: (f32, f32), c: (f32, f32), p: (f32, f32)) -> bool {
+    fn cross(o: (f32, f32), a: (f32, f32), b: (f32, f32)) -> f32 {
+        (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)
+    }
+    // degenerate triangles cover no area, without this check every point on their line would hit
+    if cross(a, b, c) == 0.0 {
+        return false;
+    }
+    let (d1, d2, d3) = (cross(a, b, p), cross(b, c, p), cross(c, a, p));
+    let has_neg = d1 < 0.0 || d2 < 0.0 || d3 < 0.0;
+    let has_pos = d1 > 0.0 || d2 > 0.0 || d3 > 0.0;
+    !(has_neg && has_pos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{mesh_contains, Aabb};
+
+    #[test]
+    fn aabb() {
+        assert_eq!(Aabb::from_points(&[]), None);
+        let aabb = Aabb::from_points(&[(0.0, 1.0), (-1.0, 0.5), (2.0, -1.0)]).unwrap();
+        assert_eq!(aabb.min, (-1.0, -1.0));
+        assert_eq!(aabb.max, (2.0, 1.0));
+        assert_eq!((aabb.width(), aabb.height()), (3.0, 2.0));
+        assert_eq!(aabb.center(), (0.5, 0.0));
+        assert!(aabb.contains((2.0, 0.0)));
+        assert!(!aabb.contains((2.5, 0.0)));
+        let other = Aabb {
+            min: (0.0, 0.0),
+            max: (3.0, 3.0),
+        };
+        assert_eq!(
+            aabb.union(&other),
+            Aabb {
+                min: (-1.0, -1.0),
+                max: (3.0, 3.0)
+            }
+        );
+    }
+
+    #[test]
+    fn mesh_hits() {
+        // a single triangle covering the lower left half of the unit square
+        let positions = [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)];
+        assert!(mesh_contains(&positions, &[0, 1, 2], (0.25, 0.25)));
+        assert!(mesh_contains(&positions, &[2, 1, 0], (0.5, 0.5)));
+        assert!(!mesh_contains(&positions, &[0, 1, 2], (0.75, 0.75)));
+        assert!(mesh_contains(&positions, &[0, 1, 2, 1, 3, 2], (0.75, 0.75)));
+        // degenerate triangles never hit
+        assert!(!mesh_contains(&positions, &[0, 1, 1], (0.5, 0.0)));
+    }
+}
@@ -0,0 +1,122 @@
+//! The canvas of a model and its coordinate systems
+
+/// Information about the canvas of a model, returned by [Model::canvas_info](./struct.Model.html#method.canvas_info).
+///
+/// Three coordinate systems are involved when working with a model:
+///
+/// * model units, which the vertex positions are in, with the y axis pointing up
+/// * canvas pixels, with `(0, 0)` in the top left corner of the canvas and the y axis pointing down
+/// * normalized device coordinates, where the canvas spans `-1..1` on both axes with the y axis pointing up
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CanvasInfo {
+    /// The size of the canvas in pixels.
+    pub size: (f32, f32),
+    /// The position of the model's origin on the canvas in pixels.
+    pub origin: (f32, f32),
+    /// The number of pixels one model unit spans.
+    pub pixels_per_unit: f32,
+}
+
+impl CanvasInfo {
+    /// Returns the size of the canvas in model units.
+    #[inline]
+    pub fn size_in_units(&self) -> (f32, f32) {
+        (
+            self.size.0 / self.pixels_per_unit,
+            self.size.1 / self.pixels_per_unit,
+        )
+    }
+
+    /// Returns the aspect ratio of the canvas, its width divided by its height.
+    #[inline]
+    pub fn aspect_ratio(&self) -> f32 {
+        self.size.0 / self.size.1
+    }
+
+    /// Converts a point in model units to canvas pixels.
+    #[inline]
+    pub fn model_to_pixels(&self, (x, y): (f32, f32)) -> (f32, f32) {
+        (
+            self.origin.0 + x * self.pixels_per_unit,
+            self.origin.1 - y * self.pixels_per_unit,
+        )
+    }
+
+    /// Converts a point in canvas pixels to model units.
+    #[inline]
+    pub fn pixels_to_model(&self, (x, y): (f32, f32)) -> (f32, f32) {
+        (
+            (x - self.origin.0) / self.pixels_per_unit,
+            (self.origin.1 - y) / self.pixels_per_unit,
+        )
+    }
+
+    /// Converts a point in canvas pixels to normalized device coordinates.
+    #[inline]
+    pub fn pixels_to_ndc(&self, (x, y): (f32, f32)) -> (f32, f32) {
+        (x / self.size.0 * 2.0 - 1.0, 1.0 - y / self.size.1 * 2.0)
+    }
+
+    /// Converts a point in normalized device coordinates to canvas pixels.
+    #[inline]
+    pub fn ndc_to_pixels(&self, (x, y): (f32, f32)) -> (f32, f32) {
+        ((x + 1.0) * 0.5 * self.size.0, (1.0 - y) * 0.5 * self.size.1)
+    }
+
+    /// Converts a point in model units to normalized device coordinates.
+    #[inline]
+    pub fn model_to_ndc(&self, point: (f32, f32)) -> (f32, f32) {
+        self.pixels_to_ndc(self.model_to_pixels(point))
+    }
+
+    /// Converts a point in normalized device coordinates to model units.
+    #[inline]
+    pub fn ndc_to_model(&self, point: (f32, f32)) -> (f32, f32) {
+        self.pixels_to_model(self.ndc_to_pixels(point))
+    }
+
+    /// Returns the column-major matrix that transforms model units to normalized device coordinates,
+    /// suitable as the model part of the renderer's mvp matrix.
+    pub fn model_to_ndc_matrix(&self) -> [[f32; 4]; 4] {
+        let sx = 2.0 * self.pixels_per_unit / self.size.0;
+        let sy = 2.0 * self.pixels_per_unit / self.size.1;
+        let (tx, ty) = self.model_to_ndc((0.0, 0.0));
+        [
+            [sx, 0.0, 0.0, 0.0],
+            [0.0, sy, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [tx, ty, 0.0, 1.0],
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CanvasInfo;
+
+    #[test]
+    fn canvas_conversions() {
+        let canvas = CanvasInfo {
+            size: (200.0, 100.0),
+            origin: (100.0, 75.0),
+            pixels_per_unit: 50.0,
+        };
+        assert_eq!(canvas.size_in_units(), (4.0, 2.0));
+        assert_eq!(canvas.aspect_ratio(), 2.0);
+        assert_eq!(canvas.model_to_pixels((0.0, 0.0)), (100.0, 75.0));
+        assert_eq!(canvas.model_to_pixels((1.0, 1.0)), (150.0, 25.0));
+        assert_eq!(canvas.pixels_to_model((150.0, 25.0)), (1.0, 1.0));
+        assert_eq!(canvas.pixels_to_ndc((0.0, 0.0)), (-1.0, 1.0));
+        assert_eq!(canvas.pixels_to_ndc((200.0, 100.0)), (1.0, -1.0));
+        assert_eq!(canvas.ndc_to_pixels((0.0, 0.0)), (100.0, 50.0));
+        assert_eq!(canvas.model_to_ndc((2.0, 1.5)), (1.0, 1.0));
+        assert_eq!(canvas.ndc_to_model((1.0, 1.0)), (2.0, 1.5));
+
+        let m = canvas.model_to_ndc_matrix();
+        let (x, y) = (2.0, 1.5);
+        assert_eq!(
+            (m[0][0] * x + m[3][0], m[1][1] * y + m[3][1]),
+            canvas.model_to_ndc((x, y))
+        );
+    }
+}
@@ -0,0 +1,234 @@
+//! Views of single drawables
+use std::iter::{Enumerate, ExactSizeIterator, FusedIterator};
+use std::ops::Range;
+use std::slice;
+
+use super::bounds::Aabb;
+use super::index::{DrawableIndex, PartIndex};
+use super::model::Model;
+use flags::{ConstantFlags, DynamicFlags};
+
+/// A view of a drawable of a [Model](./struct.Model.html), created with
+/// [Model::drawable](./struct.Model.html#method.drawable) or one of the drawable iterators.
+///
+/// This bundles the values of the `Model::drawable_*` functions for a single drawable.
+#[derive(Clone, Copy, Debug)]
+pub struct Drawable<'a> {
+    model: &'a Model,
+    index: DrawableIndex,
+}
+
+impl<'a> Drawable<'a> {
+    pub(crate) fn new(model: &'a Model, index: DrawableIndex) -> Self {
+        Drawable { model, index }
+    }
+
+    /// Returns the index of this drawable.
+    #[inline]
+    pub fn index(&self) -> DrawableIndex {
+        self.index
+    }
+
+    /// Returns the id of this drawable.
+    #[inline]
+    pub fn id(&self) -> &'a str {
+        &self.model.drawable_ids()[self.index.0]
+    }
+
+    /// Returns the [ConstantFlags](./struct.ConstantFlags.html) of this drawable.
+    #[inline]
+    pub fn constant_flags(&self) -> ConstantFlags {
+        self.model.drawable_constant_flags()[self.index.0]
+    }
+
+    /// Returns the [DynamicFlags](./struct.DynamicFlags.html) of this drawable.
+    #[inline]
+    pub fn dynamic_flags(&self) -> DynamicFlags {
+        self.model.drawable_dynamic_flags()[self.index.0]
+    }
+
+    /// Returns how this drawable changed during the last update.
+    #[inline]
+    pub fn changes(&self) -> DynamicFlags {
+        self.dynamic_flags().changes()
+    }
+
+    /// Returns whether this drawable is visible.
+    #[inline]
+    pub fn is_visible(&self) -> bool {
+        self.dynamic_flags().contains(DynamicFlags::IS_VISIBLE)
+    }
+
+    /// Returns the index of the texture this drawable uses.
+    #[inline]
+    pub fn texture_index(&self) -> usize {
+        self.model.drawable_texture_indices()[self.index.0] as usize
+    }
+
+    /// Returns the draw order of this drawable.
+    #[inline]
+    pub fn draw_order(&self) -> i32 {
+        self.model.drawable_draw_orders()[self.index.0]
+    }
+
+    /// Returns the render order of this drawable.
+    #[inline]
+    pub fn render_order(&self) -> i32 {
+        self.model.drawable_render_orders()[self.index.0]
+    }
+
+    /// Returns the opacity of this drawable.
+    #[inline]
+    pub fn opacity(&self) -> f32 {
+        self.model.drawable_opacities()[self.index.0]
+    }
+
+    /// Returns the vertex positions of this drawable.
+    #[inline]
+    pub fn vertex_positions(&self) -> &'a [(f32, f32)] {
+        self.model.drawable_vertex_positions(self.index)
+    }
+
+    /// Returns the uv coordinates of this drawable.
+    #[inline]
+    pub fn vertex_uvs(&self) -> &'a [(f32, f32)] {
+        self.model.drawable_vertex_uvs(self.index)
+    }
+
+    /// Returns the triangle indices of this drawable.
+    #[inline]
+    pub fn indices(&self) -> &'a [u16] {
+        self.model.drawable_indices(self.index)
+    }
+
+    /// Returns the bounds of this drawable in model units or `None` if it has no vertices.
+    #[inline]
+    pub fn bounds(&self) -> Option<Aabb> {
+        Aabb::from_points(self.vertex_positions())
+    }
+
+    /// Returns the indices of the drawables that mask this drawable.
+    #[inline]
+    pub fn masks(&self) -> &'a [i32] {
+        self.model.drawable_masks(self.index)
+    }
+
+    /// Returns the index of the part this drawable belongs to.
+    #[inline]
+    pub fn parent_part(&self) -> Option<PartIndex> {
+        self.model.drawable_parent_part_indices()[self.index.0].map(PartIndex)
+    }
+
+    /// Returns the multiply color this drawable should be rendered with,
+    /// see [Model::effective_multiply_color](./struct.Model.html#method.effective_multiply_color).
+    #[inline]
+    pub fn multiply_color(&self) -> [f32; 4] {
+        self.model.effective_multiply_color(self.index)
+    }
+
+    /// Returns the screen color this drawable should be rendered with,
+    /// see [Model::effective_screen_color](./struct.Model.html#method.effective_screen_color).
+    #[inline]
+    pub fn screen_color(&self) -> [f32; 4] {
+        self.model.effective_screen_color(self.index)
+    }
+}
+
+/// An iterator over the drawables of a model, created with [Model::drawables](./struct.Model.html#method.drawables)
+/// and [Model::drawables_in_render_order](./struct.Model.html#method.drawables_in_render_order).
+#[derive(Clone, Debug)]
+pub struct Drawables<'a> {
+    model: &'a Model,
+    order: Option<Vec<usize>>,
+    range: Range<usize>,
+}
+
+impl<'a> Drawables<'a> {
+    pub(crate) fn new(model: &'a Model) -> Self {
+        Drawables {
+            model,
+            order: None,
+            range: 0..model.drawable_count(),
+        }
+    }
+
+    pub(crate) fn in_render_order(model: &'a Model) -> Self {
+        let render_orders = model.drawable_render_orders();
+        let mut order: Vec<usize> = (0..model.drawable_count()).collect();
+        order.sort_by_key(|&idx| render_orders[idx]);
+        Drawables {
+            model,
+            order: Some(order),
+            range: 0..model.drawable_count(),
+        }
+    }
+
+    #[inline]
+    fn get(&self, idx: usize) -> Drawable<'a> {
+        let idx = match self.order {
+            Some(ref order) => order[idx],
+            None => idx,
+        };
+        Drawable::new(self.model, DrawableIndex(idx))
+    }
+}
+
+impl<'a> Iterator for Drawables<'a> {
+    type Item = Drawable<'a>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.range.next().map(|idx| self.get(idx))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.range.size_hint()
+    }
+}
+
+impl<'a> DoubleEndedIterator for Drawables<'a> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.range.next_back().map(|idx| self.get(idx))
+    }
+}
+
+impl<'a> ExactSizeIterator for Drawables<'a> {}
+impl<'a> FusedIterator for Drawables<'a> {}
+
+/// An iterator over the drawables that changed during the last update together with their changes,
+/// created with [Model::changed_drawables](./struct.Model.html#method.changed_drawables).
+#[derive(Clone, Debug)]
+pub struct ChangedDrawables<'a> {
+    flags: Enumerate<slice::Iter<'a, DynamicFlags>>,
+}
+
+impl<'a> ChangedDrawables<'a> {
+    pub(crate) fn new(flags: &'a [DynamicFlags]) -> Self {
+        ChangedDrawables {
+            flags: flags.iter().enumerate(),
+        }
+    }
+}
+
+impl<'a> Iterator for ChangedDrawables<'a> {
+    type Item = (DrawableIndex, DynamicFlags);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (idx, flags) = self.flags.next()?;
+            let changes = flags.changes();
+            if !changes.is_empty() {
+                return Some((DrawableIndex(idx), changes));
+            }
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.flags.size_hint().1)
+    }
+}
+
+impl<'a> FusedIterator for ChangedDrawables<'a> {}
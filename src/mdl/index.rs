@@ -0,0 +1,128 @@
+//! Typed indices and the id lookup tables
+use std::collections::HashMap;
+use std::fmt;
+
+use super::moc::Moc;
+
+/// The ids of one kind of object of a moc together with a table to look up their indices.
+#[derive(Debug)]
+pub(crate) struct IdTable {
+    ids: Vec<String>,
+    indices: HashMap<String, usize>,
+}
+
+impl IdTable {
+    pub(crate) fn new(ids: Vec<String>) -> Self {
+        let indices = ids
+            .iter()
+            .enumerate()
+            .map(|(idx, id)| (id.clone(), idx))
+            .collect();
+        IdTable { ids, indices }
+    }
+
+    #[inline]
+    pub(crate) fn ids(&self) -> &[String] {
+        &self.ids
+    }
+
+    #[inline]
+    pub(crate) fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    #[inline]
+    pub(crate) fn get(&self, id: &str) -> Option<usize> {
+        self.indices.get(id).cloned()
+    }
+}
+
+macro_rules! index_types {
+    ($($(#[$meta:meta])* $name:ident, $(#[$key_meta:meta])* $key:ident, $method:ident, $table:ident;)*) => {
+        $(
+            $(#[$meta])*
+            #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+            pub struct $name(pub usize);
+
+            impl From<usize> for $name {
+                #[inline]
+                fn from(idx: usize) -> Self {
+                    $name(idx)
+                }
+            }
+
+            impl From<$name> for usize {
+                #[inline]
+                fn from(idx: $name) -> Self {
+                    idx.0
+                }
+            }
+
+            impl fmt::Display for $name {
+                fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                    self.0.fmt(f)
+                }
+            }
+
+            $(#[$key_meta])*
+            pub trait $key: fmt::Debug {
+                /// Returns the index this key refers to in `moc` or `None` if it doesn't exist.
+                fn $method(&self, moc: &Moc) -> Option<$name>;
+            }
+
+            impl $key for $name {
+                #[inline]
+                fn $method(&self, moc: &Moc) -> Option<$name> {
+                    self.0.$method(moc)
+                }
+            }
+
+            impl $key for usize {
+                #[inline]
+                fn $method(&self, moc: &Moc) -> Option<$name> {
+                    if *self < moc.$table().len() {
+                        Some($name(*self))
+                    } else {
+                        None
+                    }
+                }
+            }
+
+            impl<'a> $key for &'a str {
+                #[inline]
+                fn $method(&self, moc: &Moc) -> Option<$name> {
+                    moc.$table().get(self).map($name)
+                }
+            }
+
+            impl $key for String {
+                #[inline]
+                fn $method(&self, moc: &Moc) -> Option<$name> {
+                    self.as_str().$method(moc)
+                }
+            }
+
+            impl<'a> $key for &'a String {
+                #[inline]
+                fn $method(&self, moc: &Moc) -> Option<$name> {
+                    self.as_str().$method(moc)
+                }
+            }
+        )*
+    };
+}
+
+index_types! {
+    /// The index of a parameter.
+    ParameterIndex,
+    /// Something that refers to a parameter, either its index or its id.
+    ParameterKey, to_parameter_index, parameter_table;
+    /// The index of a part.
+    PartIndex,
+    /// Something that refers to a part, either its index or its id.
+    PartKey, to_part_index, part_table;
+    /// The index of a drawable.
+    DrawableIndex,
+    /// Something that refers to a drawable, either its index or its id.
+    DrawableKey, to_drawable_index, drawable_table;
+}
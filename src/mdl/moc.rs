@@ -2,66 +2,164 @@
 
 use libc::c_char;
 
+use std::io::Read;
+use std::sync::Arc;
 use std::{ptr, slice};
 
 use core::{self, csmMoc, csmModel};
 
+use super::index::{
+    DrawableIndex, DrawableKey, IdTable, ParameterIndex, ParameterKey, PartIndex, PartKey,
+};
+use super::part_tree::PartTree;
 use mem::AlignedMemory;
-use Result;
+use {CubismError, Result};
+
+/// The type of a parameter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParameterType {
+    /// A normal parameter.
+    Normal,
+    /// A parameter that controls a blend shape.
+    BlendShape,
+}
+
+impl ParameterType {
+    fn from_raw(ty: core::csmParameterType) -> Self {
+        match ty {
+            core::csmParameterType_BlendShape => ParameterType::BlendShape,
+            _ => ParameterType::Normal,
+        }
+    }
+}
 
 /// This represents a moc.
 ///
-/// A moc should never exists without at least one model instance, it
-/// owns the parameter and part ids as well as the minimum, maximum and default parameter values of its [Model](./struct.Model.html).
+/// A moc is the immutable asset [Model](./struct.Model.html)s are instantiated from, it
+/// owns the parameter and part ids as well as the minimum, maximum and default parameter values of its models.
 /// The struct itself is immutable once created, so all these values never change either.
+/// Everything is copied out of the core's memory when the moc is created, so the returned slices only
+/// borrow from the moc itself.
 ///
-/// You normally never have to interact with it on it's own.
+/// A moc is shared between all of its models, load it once with [from_bytes](#method.from_bytes) and
+/// create as many models as needed with [Model::new](./struct.Model.html#method.new).
+/// As it is immutable, a moc is `Send` and `Sync` and its models can live on different threads.
 #[derive(Debug)]
 pub struct Moc {
     mem: AlignedMemory<csmMoc>,
-    part_ids: Vec<&'static str>,
-    param_ids: Vec<&'static str>,
-    drawable_ids: Vec<&'static str>,
-    param_def_val: &'static [f32],
-    param_max_val: &'static [f32],
-    param_min_val: &'static [f32],
+    version: u32,
+    part_ids: IdTable,
+    param_ids: IdTable,
+    drawable_ids: IdTable,
+    param_def_val: Vec<f32>,
+    param_max_val: Vec<f32>,
+    param_min_val: Vec<f32>,
+    param_types: Vec<ParameterType>,
+    param_key_values: Vec<Vec<f32>>,
+    param_repeats: Vec<bool>,
+    part_parents: Vec<Option<usize>>,
+    drawable_parent_parts: Vec<Option<usize>>,
+    part_tree: PartTree,
 }
 
 impl Moc {
+    /// Returns the version of the moc file format this moc was created with
+    #[inline]
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
     /// Returns the part names
     #[inline]
-    pub fn part_ids(&self) -> &[&str] {
-        &self.part_ids
+    pub fn part_ids(&self) -> &[String] {
+        self.part_ids.ids()
     }
 
     /// Returns the parameter names
     #[inline]
-    pub fn parameter_ids(&self) -> &[&str] {
-        &self.param_ids
+    pub fn parameter_ids(&self) -> &[String] {
+        self.param_ids.ids()
     }
 
     /// Returns the drawable names
     #[inline]
-    pub fn drawable_ids(&self) -> &[&str] {
-        &self.drawable_ids
+    pub fn drawable_ids(&self) -> &[String] {
+        self.drawable_ids.ids()
+    }
+
+    /// Returns the index of the parameter `key` refers to or `None` if it does not exist in this moc.
+    ///
+    /// Ids are looked up in a hash table, so this is cheap enough to be called every frame.
+    #[inline]
+    pub fn parameter_index<K: ParameterKey>(&self, key: K) -> Option<ParameterIndex> {
+        key.to_parameter_index(self)
+    }
+
+    /// Returns the index of the part `key` refers to or `None` if it does not exist in this moc.
+    #[inline]
+    pub fn part_index<K: PartKey>(&self, key: K) -> Option<PartIndex> {
+        key.to_part_index(self)
+    }
+
+    /// Returns the index of the drawable `key` refers to or `None` if it does not exist in this moc.
+    #[inline]
+    pub fn drawable_index<K: DrawableKey>(&self, key: K) -> Option<DrawableIndex> {
+        key.to_drawable_index(self)
     }
 
     /// Returns the parameter max values
     #[inline]
     pub fn parameter_max(&self) -> &[f32] {
-        self.param_max_val
+        &self.param_max_val
     }
 
     /// Returns the parameter min values
     #[inline]
     pub fn parameter_min(&self) -> &[f32] {
-        self.param_min_val
+        &self.param_min_val
     }
 
     /// Returns the parameter default values
     #[inline]
     pub fn parameter_default(&self) -> &[f32] {
-        self.param_def_val
+        &self.param_def_val
+    }
+
+    /// Returns the parameter types
+    #[inline]
+    pub fn parameter_types(&self) -> &[ParameterType] {
+        &self.param_types
+    }
+
+    /// Returns the key values of the specified parameter, these are the values at which
+    /// the parameter has keyforms.
+    #[inline]
+    pub fn parameter_key_values<K: ParameterKey>(&self, key: K) -> &[f32] {
+        &self.param_key_values[self.expect_parameter(key)]
+    }
+
+    /// Returns whether the parameters repeat, a repeating parameter wraps around when it exceeds its range
+    #[inline]
+    pub fn parameter_repeats(&self) -> &[bool] {
+        &self.param_repeats
+    }
+
+    /// Returns the parent part indices of the parts, `None` if the part is a root part
+    #[inline]
+    pub fn part_parent_indices(&self) -> &[Option<usize>] {
+        &self.part_parents
+    }
+
+    /// Returns the parent part indices of the drawables, `None` if the drawable belongs to no part
+    #[inline]
+    pub fn drawable_parent_part_indices(&self) -> &[Option<usize>] {
+        &self.drawable_parent_parts
+    }
+
+    /// Returns the hierarchy of the parts
+    #[inline]
+    pub fn part_tree(&self) -> &PartTree {
+        &self.part_tree
     }
 
     /// Returns the number of parameters this moc has
@@ -75,29 +173,111 @@ impl Moc {
     pub fn part_count(&self) -> usize {
         self.part_ids.len()
     }
+
+    pub(crate) fn parameter_table(&self) -> &IdTable {
+        &self.param_ids
+    }
+
+    pub(crate) fn part_table(&self) -> &IdTable {
+        &self.part_ids
+    }
+
+    pub(crate) fn drawable_table(&self) -> &IdTable {
+        &self.drawable_ids
+    }
+
+    /// Resolves `key` to a parameter index, panicking if it doesn't exist just like indexing a slice would.
+    pub(crate) fn expect_parameter<K: ParameterKey>(&self, key: K) -> usize {
+        match key.to_parameter_index(self) {
+            Some(idx) => idx.0,
+            None => panic!("{:?} is not a parameter of this moc", key),
+        }
+    }
+
+    /// Resolves `key` to a part index, panicking if it doesn't exist just like indexing a slice would.
+    pub(crate) fn expect_part<K: PartKey>(&self, key: K) -> usize {
+        match key.to_part_index(self) {
+            Some(idx) => idx.0,
+            None => panic!("{:?} is not a part of this moc", key),
+        }
+    }
+
+    /// Resolves `key` to a drawable index, panicking if it doesn't exist just like indexing a slice would.
+    pub(crate) fn expect_drawable<K: DrawableKey>(&self, key: K) -> usize {
+        match key.to_drawable_index(self) {
+            Some(idx) => idx.0,
+            None => panic!("{:?} is not a drawable of this moc", key),
+        }
+    }
 }
 
 impl Moc {
-    pub(crate) fn new(data: &[u8]) -> Result<Self> {
+    /// Creates a moc from byte data.
+    ///
+    /// The data is validated by the core before it is used, data that isn't a moc, a moc that is newer than
+    /// what the linked core supports or a moc that fails the consistency check result in an error.
+    pub fn from_bytes(data: &[u8]) -> Result<Arc<Self>> {
+        Self::new(data).map(Arc::new)
+    }
+
+    /// Creates a moc from a reader instance
+    #[inline]
+    pub fn from_reader<R: Read>(reader: &mut R) -> Result<Arc<Self>> {
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf)?;
+        Self::from_bytes(&buf)
+    }
+
+    fn new(data: &[u8]) -> Result<Self> {
+        #[cfg(feature = "dynamic")]
+        {
+            if !core::is_core_loaded() {
+                return Err(CubismError::CoreNotLoaded);
+            }
+        }
+        if data.is_empty() {
+            return Err(CubismError::InvalidMoc);
+        }
         let mut mem = AlignedMemory::new(data.len())?;
-        unsafe {
+        let size = mem.layout().size() as u32;
+        let version = unsafe {
             ptr::copy(data.as_ptr(), mem.as_mut_ptr() as *mut u8, data.len());
-            if core::csmReviveMocInPlace(mem.as_mut_ptr() as *mut _, mem.layout().size() as u32)
-                .is_null()
-            {
-                unreachable!("core::csmReviveMocInPlace return a null pointer, this shouldn't happen unless the alignment is incorrect")
-            } else {
-                Ok(Moc {
-                    mem,
-                    part_ids: Vec::new(),
-                    param_ids: Vec::new(),
-                    drawable_ids: Vec::new(),
-                    param_def_val: slice::from_raw_parts(0x1 as *const f32, 0),
-                    param_max_val: slice::from_raw_parts(0x1 as *const f32, 0),
-                    param_min_val: slice::from_raw_parts(0x1 as *const f32, 0),
-                })
+            let version = core::csmGetMocVersion(mem.as_ptr() as *const _, size);
+            if version == core::csmMocVersion_Unknown {
+                return Err(CubismError::InvalidMoc);
             }
-        }
+            let latest = core::csmGetLatestMocVersion();
+            if version > latest {
+                return Err(CubismError::UnsupportedMocVersion { version, latest });
+            }
+            if core::csmHasMocConsistency(mem.as_mut_ptr() as *mut _, size) == 0 {
+                return Err(CubismError::InconsistentMoc);
+            }
+            if core::csmReviveMocInPlace(mem.as_mut_ptr() as *mut _, size).is_null() {
+                return Err(CubismError::InvalidMoc);
+            }
+            version
+        };
+        let mut moc = Moc {
+            mem,
+            version,
+            part_ids: IdTable::new(Vec::new()),
+            param_ids: IdTable::new(Vec::new()),
+            drawable_ids: IdTable::new(Vec::new()),
+            param_def_val: Vec::new(),
+            param_max_val: Vec::new(),
+            param_min_val: Vec::new(),
+            param_types: Vec::new(),
+            param_key_values: Vec::new(),
+            param_repeats: Vec::new(),
+            part_parents: Vec::new(),
+            drawable_parent_parts: Vec::new(),
+            part_tree: PartTree::default(),
+        };
+        // the core only exposes the moc's data through a model, so a temporary one is needed
+        let model = moc.init_new_model()?;
+        moc.init_ids(&model)?;
+        Ok(moc)
     }
 
     /// Returns the raw [csmMoc](../cubism_core_sys/moc/struct.csmMoc.html) ptr
@@ -112,46 +292,82 @@ impl Moc {
         self.mem.as_mut_ptr()
     }
 
-    /// Called once when the first model of a moc is created to initialize the shared `str` storage
-    pub(crate) fn init_ids(&mut self, model: &AlignedMemory<csmModel>) -> Result<()> {
-        debug_assert!(self.param_ids.is_empty()); //Make sure that this hasnt been called before
+    /// Called once when the moc is created to copy the ids and the model independent values
+    fn init_ids(&mut self, model: &AlignedMemory<csmModel>) -> Result<()> {
+        debug_assert!(self.param_def_val.is_empty()); //Make sure that this hasnt been called before
         unsafe {
             let param_count = core::csmGetParameterCount(model.as_ptr()) as usize;
             let param_ids = core::csmGetParameterIds(model.as_ptr());
-            self.param_ids = Self::init_id_vec(param_ids, param_count)?;
+            self.param_ids = IdTable::new(Self::init_id_vec(param_ids, param_count)?);
             let part_count = core::csmGetPartCount(model.as_ptr()) as usize;
             let part_ids = core::csmGetPartIds(model.as_ptr());
-            self.part_ids = Self::init_id_vec(part_ids, part_count)?;
+            self.part_ids = IdTable::new(Self::init_id_vec(part_ids, part_count)?);
             let drawable_count = core::csmGetDrawableCount(model.as_ptr()) as usize;
             let drawable_ids = core::csmGetDrawableIds(model.as_ptr());
-            self.drawable_ids = Self::init_id_vec(drawable_ids, drawable_count)?;
+            self.drawable_ids = IdTable::new(Self::init_id_vec(drawable_ids, drawable_count)?);
             self.param_def_val = slice::from_raw_parts(
                 core::csmGetParameterDefaultValues(model.as_ptr()),
                 param_count,
-            );
+            )
+            .to_vec();
             self.param_max_val = slice::from_raw_parts(
                 core::csmGetParameterMaximumValues(model.as_ptr()),
                 param_count,
-            );
+            )
+            .to_vec();
             self.param_min_val = slice::from_raw_parts(
                 core::csmGetParameterMinimumValues(model.as_ptr()),
                 param_count,
+            )
+            .to_vec();
+            self.param_types =
+                slice::from_raw_parts(core::csmGetParameterTypes(model.as_ptr()), param_count)
+                    .iter()
+                    .map(|&ty| ParameterType::from_raw(ty))
+                    .collect();
+            let key_counts =
+                slice::from_raw_parts(core::csmGetParameterKeyCounts(model.as_ptr()), param_count);
+            let key_values =
+                slice::from_raw_parts(core::csmGetParameterKeyValues(model.as_ptr()), param_count);
+            self.param_key_values = key_values
+                .iter()
+                .zip(key_counts)
+                .map(|(&ptr, &count)| slice::from_raw_parts(ptr, count as usize).to_vec())
+                .collect();
+            self.param_repeats =
+                slice::from_raw_parts(core::csmGetParameterRepeats(model.as_ptr()), param_count)
+                    .iter()
+                    .map(|&repeat| repeat != 0)
+                    .collect();
+            self.part_parents = Self::init_index_vec(
+                core::csmGetPartParentPartIndices(model.as_ptr()),
+                part_count,
             );
+            self.drawable_parent_parts = Self::init_index_vec(
+                core::csmGetDrawableParentPartIndices(model.as_ptr()),
+                drawable_count,
+            );
+            self.part_tree = PartTree::new(&self.part_parents, &self.drawable_parent_parts);
         }
         Ok(())
     }
 
-    fn init_id_vec(ptr: *mut *const c_char, len: usize) -> Result<Vec<&'static str>> {
+    unsafe fn init_id_vec(ptr: *mut *const c_char, len: usize) -> Result<Vec<String>> {
         use std::ffi::CStr;
         let mut out = Vec::with_capacity(len);
-        for ptr in unsafe { slice::from_raw_parts_mut(ptr, len).iter() } {
-            unsafe {
-                out.push(CStr::from_ptr(*ptr).to_str()?);
-            }
+        for &ptr in slice::from_raw_parts(ptr, len) {
+            out.push(CStr::from_ptr(ptr).to_str()?.to_owned());
         }
         Ok(out)
     }
 
+    unsafe fn init_index_vec(ptr: *const i32, len: usize) -> Vec<Option<usize>> {
+        slice::from_raw_parts(ptr, len)
+            .iter()
+            .map(|&idx| if idx < 0 { None } else { Some(idx as usize) })
+            .collect()
+    }
+
     ///Creates a new model memory instance from this moc
     pub(crate) fn init_new_model(&self) -> Result<AlignedMemory<csmModel>> {
         unsafe {
@@ -162,9 +378,10 @@ impl Moc {
                 self.mem.as_ptr(),
                 model_mem.as_mut_ptr() as *mut _,
                 model_size,
-            ).is_null()
+            )
+            .is_null()
             {
-                unreachable!("core::csmInitializeModelInPlace return a null pointer, this shouldn't happen unless the alignment is incorrect")
+                Err("core::csmInitializeModelInPlace returned a null pointer".into())
             } else {
                 Ok(model_mem)
             }
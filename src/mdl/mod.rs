@@ -1,5 +1,19 @@
+mod bounds;
+mod canvas;
+mod drawable;
+mod index;
 mod moc;
 mod model;
+mod parameter;
+mod part_tree;
 
-pub use self::moc::Moc;
+pub use self::bounds::Aabb;
+pub use self::canvas::CanvasInfo;
+pub use self::drawable::{ChangedDrawables, Drawable, Drawables};
+pub use self::index::{
+    DrawableIndex, DrawableKey, ParameterIndex, ParameterKey, PartIndex, PartKey,
+};
+pub use self::moc::{Moc, ParameterType};
 pub use self::model::Model;
+pub use self::parameter::{Parameter, ParameterMut, Parameters, ParametersMut};
+pub use self::part_tree::{Ancestors, PartTree, Subtree};
@@ -1,104 +1,307 @@
 //! The model struct
+use std::collections::HashMap;
 use std::io::Read;
 use std::ops;
-use std::rc::Rc;
 use std::slice;
+use std::sync::Arc;
 
 use mem::AlignedMemory;
 
+use super::bounds::{self, Aabb};
+use super::canvas::CanvasInfo;
+use super::drawable::{ChangedDrawables, Drawable, Drawables};
+use super::index::{DrawableIndex, DrawableKey, ParameterIndex, ParameterKey, PartIndex, PartKey};
 use super::moc::Moc;
+use super::parameter::{Parameter, ParameterMut, Parameters, ParametersMut};
 use core::{self, csmModel};
 use flags::{ConstantFlags, DynamicFlags};
 use CubismError;
 
 /// This represents a model.
 ///
-/// A model shares its underlying [Moc](./struct.Moc.html) with other models that have been cloned from this one and
+/// A model shares its underlying [Moc](./struct.Moc.html) with all other models created from the same moc and
 /// has control over it's own parameters and part opacities.
 ///
+/// A model is `Send`, so it can be created on one thread and updated on another. The core only reads from a model
+/// through shared references, so sharing one between threads for reading is fine as well.
+///
 /// Slices returned by functions have to be indexed by the drawable, parameter or part index for the individual value.
 /// If the functions takes an index argument, then the function index replaces this behaviour and the returned slice are values that all belong to the drawable.
+/// Functions taking a parameter, part or drawable accept either its index or its id, see [ParameterKey](./trait.ParameterKey.html).
+/// They panic if the object doesn't exist, use [parameter_index](./struct.Moc.html#method.parameter_index) and co. to check beforehand.
+/// All returned slices borrow from the model, so they can't be held on to across a call to [update](#method.update).
 #[derive(Debug)]
 pub struct Model {
     mem: AlignedMemory<csmModel>,
-    moc: Rc<Moc>,
-    param_values: &'static mut [f32],
-    part_opacities: &'static mut [f32],
+    moc: Arc<Moc>,
     drawable_count: usize,
+    flags_need_reset: bool,
+    multiply_overrides: ColorOverrides,
+    screen_overrides: ColorOverrides,
+    virtual_parameters: HashMap<String, f32>,
 }
 
-impl Model {
-    /// Returns the parameter index of `name` or `None` if the parameter name does not exist in this model.
-    #[inline]
-    pub fn parameter_index(&self, name: &str) -> Option<usize> {
-        self.parameter_ids().iter().position(|id| *id == name)
-    }
+/// User specified colors that replace the colors computed by the core.
+#[derive(Clone, Debug)]
+struct ColorOverrides {
+    drawables: Vec<Option<[f32; 4]>>,
+    parts: Vec<Option<[f32; 4]>>,
+}
 
-    /// Returns the part index of `name` or `None` if the part name does not exist in this model.
-    #[inline]
-    pub fn part_index(&self, name: &str) -> Option<usize> {
-        self.part_ids().iter().position(|id| *id == name)
+impl ColorOverrides {
+    fn new(drawable_count: usize, part_count: usize) -> Self {
+        ColorOverrides {
+            drawables: vec![None; drawable_count],
+            parts: vec![None; part_count],
+        }
     }
+}
 
+impl Model {
     /// Returns the parameter values.
     #[inline]
     pub fn parameter_values(&self) -> &[f32] {
-        self.param_values
+        unsafe {
+            slice::from_raw_parts(
+                core::csmGetParameterValues(self.mem.as_ptr() as *mut _),
+                self.parameter_count(),
+            )
+        }
     }
 
     /// Returns the parameter values.
     #[inline]
     pub fn parameter_values_mut(&mut self) -> &mut [f32] {
-        self.param_values
+        self.split_parameter_values_mut().1
+    }
+
+    /// Borrows the moc and the parameter values at the same time.
+    pub(crate) fn split_parameter_values_mut(&mut self) -> (&Moc, &mut [f32]) {
+        let values = unsafe {
+            slice::from_raw_parts_mut(
+                core::csmGetParameterValues(self.mem.as_mut_ptr()),
+                self.moc.parameter_count(),
+            )
+        };
+        (&self.moc, values)
+    }
+
+    /// Blends the value of the specified parameter towards `val` by `weight`,
+    /// see [ParameterMut::set_value_weighted](./struct.ParameterMut.html#method.set_value_weighted).
+    ///
+    /// Unlike [set_parameter_value](#method.set_parameter_value) the value is limited to the range of the parameter.
+    #[inline]
+    pub fn set_parameter_value_weighted<K: ParameterKey>(&mut self, key: K, val: f32, weight: f32) {
+        self.expect_parameter_mut(key)
+            .set_value_weighted(val, weight);
+    }
+
+    /// Adds `val` scaled by `weight` to the value of the specified parameter,
+    /// see [ParameterMut::add_value](./struct.ParameterMut.html#method.add_value).
+    #[inline]
+    pub fn add_parameter_value<K: ParameterKey>(&mut self, key: K, val: f32, weight: f32) {
+        self.expect_parameter_mut(key).add_value(val, weight);
     }
 
-    /// Sets the parameter value at index `idx` to `val`.
+    /// Multiplies the value of the specified parameter with `val` blended by `weight`,
+    /// see [ParameterMut::multiply_value](./struct.ParameterMut.html#method.multiply_value).
     #[inline]
-    pub fn set_parameter_value(&mut self, idx: usize, val: f32) {
-        self.param_values[idx] = val;
+    pub fn multiply_parameter_value<K: ParameterKey>(&mut self, key: K, val: f32, weight: f32) {
+        self.expect_parameter_mut(key).multiply_value(val, weight);
+    }
+
+    fn expect_parameter_mut<K: ParameterKey>(&mut self, key: K) -> ParameterMut<'_> {
+        let idx = ParameterIndex(self.moc.expect_parameter(key));
+        let (moc, values) = self.split_parameter_values_mut();
+        ParameterMut::new(moc, idx, &mut values[idx.0])
+    }
+
+    /// Returns a view of the specified parameter or `None` if it doesn't exist.
+    #[inline]
+    pub fn parameter<K: ParameterKey>(&self, key: K) -> Option<Parameter<'_>> {
+        let idx = key.to_parameter_index(&self.moc)?;
+        Some(Parameter::new(
+            &self.moc,
+            idx,
+            &self.parameter_values()[idx.0],
+        ))
+    }
+
+    /// Returns a mutable view of the specified parameter or `None` if it doesn't exist.
+    #[inline]
+    pub fn parameter_mut<K: ParameterKey>(&mut self, key: K) -> Option<ParameterMut<'_>> {
+        let idx = key.to_parameter_index(&self.moc)?;
+        let (moc, values) = self.split_parameter_values_mut();
+        Some(ParameterMut::new(moc, idx, &mut values[idx.0]))
+    }
+
+    /// Returns an iterator over views of all parameters.
+    #[inline]
+    pub fn parameters(&self) -> Parameters<'_> {
+        Parameters::new(&self.moc, self.parameter_values())
+    }
+
+    /// Returns an iterator over mutable views of all parameters.
+    #[inline]
+    pub fn parameters_mut(&mut self) -> ParametersMut<'_> {
+        let (moc, values) = self.split_parameter_values_mut();
+        ParametersMut::new(moc, values)
+    }
+
+    /// Resets all parameters to their default values.
+    #[inline]
+    pub fn reset_parameters(&mut self) {
+        let (moc, values) = self.split_parameter_values_mut();
+        values.copy_from_slice(moc.parameter_default());
+    }
+
+    /// Returns the value of the specified parameter.
+    #[inline]
+    pub fn parameter_value<K: ParameterKey>(&self, key: K) -> f32 {
+        self.parameter_values()[self.moc.expect_parameter(key)]
+    }
+
+    /// Sets the value of the specified parameter to `val`.
+    #[inline]
+    pub fn set_parameter_value<K: ParameterKey>(&mut self, key: K, val: f32) {
+        let idx = self.moc.expect_parameter(key);
+        self.parameter_values_mut()[idx] = val;
+    }
+
+    /// Returns the value of the parameter `id`, or of the virtual parameter `id` if the moc has no such
+    /// parameter, `None` if neither exists.
+    ///
+    /// Like in the official framework the model keeps virtual parameters for ids its moc lacks, motions
+    /// write the opacity curves of parts into the parameter with the id of the part and the
+    /// [Pose](./struct.Pose.html) fades the parts from there.
+    pub fn virtual_parameter_value(&self, id: &str) -> Option<f32> {
+        match self.parameter(id) {
+            Some(param) => Some(param.value()),
+            None => self.virtual_parameters.get(id).cloned(),
+        }
+    }
+
+    /// Sets the parameter `id` to `val`, creating a virtual parameter if the moc has no such parameter.
+    pub fn set_virtual_parameter_value(&mut self, id: &str, val: f32) {
+        match self.parameter_mut(id) {
+            Some(mut param) => param.set_value(val),
+            None => {
+                self.virtual_parameters.insert(id.to_owned(), val);
+            }
+        }
     }
 
     /// Returns the part opacities.
     #[inline]
     pub fn part_opacities(&self) -> &[f32] {
-        self.part_opacities
+        unsafe {
+            slice::from_raw_parts(
+                core::csmGetPartOpacities(self.mem.as_ptr() as *mut _),
+                self.part_count(),
+            )
+        }
     }
 
     /// Returns the part opacities.
     #[inline]
     pub fn part_opacities_mut(&mut self) -> &mut [f32] {
-        self.part_opacities
+        unsafe {
+            slice::from_raw_parts_mut(
+                core::csmGetPartOpacities(self.mem.as_mut_ptr()),
+                self.part_count(),
+            )
+        }
+    }
+
+    /// Returns the opacity of the specified part.
+    #[inline]
+    pub fn part_opacity<K: PartKey>(&self, key: K) -> f32 {
+        self.part_opacities()[self.moc.expect_part(key)]
     }
 
-    /// Sets the part opacity at index `idx` to `val`.
+    /// Sets the opacity of the specified part to `val`.
     #[inline]
-    pub fn set_part_opacity(&mut self, idx: usize, val: f32) {
-        self.part_opacities[idx] = val;
+    pub fn set_part_opacity<K: PartKey>(&mut self, key: K, val: f32) {
+        let idx = self.moc.expect_part(key);
+        self.part_opacities_mut()[idx] = val;
+    }
+
+    /// Returns the opacity of the specified part multiplied with the opacities of all its ancestors,
+    /// which is the opacity the core applies to the drawables of the part.
+    pub fn cascaded_part_opacity<K: PartKey>(&self, key: K) -> f32 {
+        let idx = PartIndex(self.moc.expect_part(key));
+        let opacities = self.part_opacities();
+        self.part_tree()
+            .ancestors(idx)
+            .fold(opacities[idx.0], |opacity, part| {
+                opacity * opacities[part.0]
+            })
+    }
+
+    /// Sets the opacity of the specified part and all of its descendants to `val`.
+    pub fn set_subtree_opacity<K: PartKey>(&mut self, key: K, val: f32) {
+        let idx = PartIndex(self.moc.expect_part(key));
+        let opacities = unsafe {
+            slice::from_raw_parts_mut(
+                core::csmGetPartOpacities(self.mem.as_mut_ptr()),
+                self.moc.part_count(),
+            )
+        };
+        for part in self.moc.part_tree().subtree(idx) {
+            opacities[part.0] = val;
+        }
+    }
+
+    /// Shows or hides the specified part and all of its descendants by setting their opacities to 1 or 0.
+    #[inline]
+    pub fn set_subtree_visible<K: PartKey>(&mut self, key: K, visible: bool) {
+        self.set_subtree_opacity(key, if visible { 1.0 } else { 0.0 });
     }
 
     /// Updates this model and finalizes its parameters and part opacities.
-    /// This has to be called before accessing the drawables because it updates them and reset their dynamic flags.
+    /// This has to be called before accessing the drawables because it updates them.
+    ///
+    /// The dynamic flags of the drawables are reset at the start of the update, so afterwards they describe
+    /// what changed during this update, see [changed_drawables](#method.changed_drawables).
+    /// The flags after the first update report everything as changed.
     #[inline]
     pub fn update(&mut self) {
+        if self.flags_need_reset {
+            self.reset_dynamic_flags();
+        }
         unsafe { core::csmUpdateModel(self.mem.as_mut_ptr()) };
+        self.flags_need_reset = true;
+    }
+
+    /// Resets the dynamic flags of the drawables, leaving only `IS_VISIBLE`.
+    ///
+    /// This is done by [update](#method.update) automatically, calling it between updates marks all changes as seen.
+    #[inline]
+    pub fn reset_dynamic_flags(&mut self) {
         unsafe { core::csmResetDrawableDynamicFlags(self.mem.as_mut_ptr()) };
+        self.flags_need_reset = false;
+    }
+
+    /// Returns an iterator over the drawables that changed during the last update and how they changed.
+    #[inline]
+    pub fn changed_drawables(&self) -> ChangedDrawables<'_> {
+        ChangedDrawables::new(self.drawable_dynamic_flags())
     }
 
     /// Returns information about this models size, origin and pixels-per-unit.
     #[inline]
-    pub fn canvas_info(&self) -> ((f32, f32), (f32, f32), f32) {
-        let mut size = (0.0, 0.0);
-        let mut origin = (0.0, 0.0);
+    pub fn canvas_info(&self) -> CanvasInfo {
+        let mut size = core::csmVector2 { x: 0.0, y: 0.0 };
+        let mut origin = core::csmVector2 { x: 0.0, y: 0.0 };
         let mut ppu = 0.0;
         unsafe {
-            core::csmReadCanvasInfo(
-                self.mem.as_ptr(),
-                &mut size as *mut (f32, f32) as *mut _,
-                &mut origin as *mut (f32, f32) as *mut _,
-                &mut ppu,
-            );
+            core::csmReadCanvasInfo(self.mem.as_ptr(), &mut size, &mut origin, &mut ppu);
+        }
+        CanvasInfo {
+            size: (size.x, size.y),
+            origin: (origin.x, origin.y),
+            pixels_per_unit: ppu,
         }
-        (size, origin, ppu)
     }
 
     /// Returns the number of drawables of this model.
@@ -107,6 +310,76 @@ impl Model {
         self.drawable_count
     }
 
+    /// Returns a view of the specified drawable or `None` if it doesn't exist.
+    #[inline]
+    pub fn drawable<K: DrawableKey>(&self, key: K) -> Option<Drawable<'_>> {
+        key.to_drawable_index(&self.moc)
+            .map(|idx| Drawable::new(self, idx))
+    }
+
+    /// Returns an iterator over views of all drawables in index order.
+    #[inline]
+    pub fn drawables(&self) -> Drawables<'_> {
+        Drawables::new(self)
+    }
+
+    /// Returns an iterator over views of all drawables in the order they have to be rendered in,
+    /// back to front.
+    #[inline]
+    pub fn drawables_in_render_order(&self) -> Drawables<'_> {
+        Drawables::in_render_order(self)
+    }
+
+    /// Returns the bounds of the specified drawable in model units or `None` if it has no vertices.
+    #[inline]
+    pub fn drawable_bounds<K: DrawableKey>(&self, key: K) -> Option<Aabb> {
+        Aabb::from_points(self.drawable_vertex_positions(key))
+    }
+
+    /// Returns the bounds of all visible drawables in model units or `None` if no drawable is visible.
+    pub fn bounds(&self) -> Option<Aabb> {
+        self.drawables()
+            .filter(|drawable| drawable.is_visible())
+            .filter_map(|drawable| drawable.bounds())
+            .fold(None, |acc: Option<Aabb>, aabb| {
+                Some(acc.map_or(aabb, |acc| acc.union(&aabb)))
+            })
+    }
+
+    /// Returns true if `point`, in model units, lies on one of the triangles of the specified drawable.
+    ///
+    /// If the drawable is masked the point also has to lie on one of its masks.
+    /// This only looks at the geometry, visibility and opacity are ignored.
+    pub fn hit_test_drawable<K: DrawableKey>(&self, key: K, point: (f32, f32)) -> bool {
+        let idx = self.moc.expect_drawable(key);
+        let on_mesh = |idx: usize| match self.drawable_bounds(idx) {
+            Some(aabb) => {
+                aabb.contains(point)
+                    && bounds::mesh_contains(
+                        self.drawable_vertex_positions(idx),
+                        self.drawable_indices(idx),
+                        point,
+                    )
+            }
+            None => false,
+        };
+        let masks = self.drawable_masks(idx);
+        on_mesh(idx) && (masks.is_empty() || masks.iter().any(|&mask| on_mesh(mask as usize)))
+    }
+
+    /// Returns the front most visible drawable at `point`, in model units, or `None` if there is none.
+    ///
+    /// Drawables are tested in reverse render order and are skipped if they are invisible or fully transparent,
+    /// see [hit_test_drawable](#method.hit_test_drawable) for how a single drawable is tested.
+    /// Use [CanvasInfo](./struct.CanvasInfo.html) to convert a point from pixels to model units first.
+    pub fn hit_test(&self, point: (f32, f32)) -> Option<DrawableIndex> {
+        self.drawables_in_render_order()
+            .rev()
+            .filter(|drawable| drawable.is_visible() && drawable.opacity() > 0.0)
+            .find(|drawable| self.hit_test_drawable(drawable.index(), point))
+            .map(|drawable| drawable.index())
+    }
+
     /// Returns the render orders of the drawables.
     #[inline]
     pub fn drawable_render_orders(&self) -> &[i32] {
@@ -151,9 +424,10 @@ impl Model {
         }
     }
 
-    /// Returns the indices of the drawable at the specified index.
+    /// Returns the indices of the specified drawable.
     #[inline]
-    pub fn drawable_indices(&self, idx: usize) -> &[u16] {
+    pub fn drawable_indices<K: DrawableKey>(&self, key: K) -> &[u16] {
+        let idx = self.moc.expect_drawable(key);
         unsafe {
             slice::from_raw_parts(
                 *core::csmGetDrawableIndices(self.as_ptr()).offset(idx as isize),
@@ -173,9 +447,10 @@ impl Model {
         }
     }
 
-    /// Returns the vertex positions of the drawable at the specified index.
+    /// Returns the vertex positions of the specified drawable.
     #[inline]
-    pub fn drawable_vertex_positions(&self, idx: usize) -> &[(f32, f32)] {
+    pub fn drawable_vertex_positions<K: DrawableKey>(&self, key: K) -> &[(f32, f32)] {
+        let idx = self.moc.expect_drawable(key);
         unsafe {
             slice::from_raw_parts(
                 *core::csmGetDrawableVertexPositions(self.as_ptr()).offset(idx as isize)
@@ -185,9 +460,10 @@ impl Model {
         }
     }
 
-    /// Returns the uv coordinates of the drawable at the specified index.
+    /// Returns the uv coordinates of the specified drawable.
     #[inline]
-    pub fn drawable_vertex_uvs(&self, idx: usize) -> &[(f32, f32)] {
+    pub fn drawable_vertex_uvs<K: DrawableKey>(&self, key: K) -> &[(f32, f32)] {
+        let idx = self.moc.expect_drawable(key);
         unsafe {
             slice::from_raw_parts(
                 *core::csmGetDrawableVertexUvs(self.as_ptr()).offset(idx as isize) as *const _,
@@ -217,9 +493,10 @@ impl Model {
         }
     }
 
-    /// Returns the mask of the drawable at the specified index.
+    /// Returns the mask of the specified drawable.
     #[inline]
-    pub fn drawable_masks(&self, idx: usize) -> &[i32] {
+    pub fn drawable_masks<K: DrawableKey>(&self, key: K) -> &[i32] {
+        let idx = self.moc.expect_drawable(key);
         unsafe {
             slice::from_raw_parts(
                 slice::from_raw_parts(
@@ -238,6 +515,102 @@ impl Model {
         (0..self.drawable_count).any(|i| maskcounts[i] <= 0)
     }
 
+    /// Returns the multiply colors of the drawables as computed by the core, in rgba order.
+    ///
+    /// These don't include the overrides set with [set_drawable_multiply_color](#method.set_drawable_multiply_color)
+    /// and [set_part_multiply_color](#method.set_part_multiply_color), see [effective_multiply_color](#method.effective_multiply_color).
+    #[inline]
+    pub fn drawable_multiply_colors(&self) -> &[[f32; 4]] {
+        unsafe {
+            slice::from_raw_parts(
+                core::csmGetDrawableMultiplyColors(self.as_ptr()) as *const _,
+                self.drawable_count,
+            )
+        }
+    }
+
+    /// Returns the screen colors of the drawables as computed by the core, in rgba order.
+    ///
+    /// These don't include the overrides set with [set_drawable_screen_color](#method.set_drawable_screen_color)
+    /// and [set_part_screen_color](#method.set_part_screen_color), see [effective_screen_color](#method.effective_screen_color).
+    #[inline]
+    pub fn drawable_screen_colors(&self) -> &[[f32; 4]] {
+        unsafe {
+            slice::from_raw_parts(
+                core::csmGetDrawableScreenColors(self.as_ptr()) as *const _,
+                self.drawable_count,
+            )
+        }
+    }
+
+    /// Overrides the multiply color of the specified drawable, `None` removes the override.
+    #[inline]
+    pub fn set_drawable_multiply_color<K: DrawableKey>(&mut self, key: K, color: Option<[f32; 4]>) {
+        self.multiply_overrides.drawables[self.moc.expect_drawable(key)] = color;
+    }
+
+    /// Overrides the screen color of the specified drawable, `None` removes the override.
+    #[inline]
+    pub fn set_drawable_screen_color<K: DrawableKey>(&mut self, key: K, color: Option<[f32; 4]>) {
+        self.screen_overrides.drawables[self.moc.expect_drawable(key)] = color;
+    }
+
+    /// Overrides the multiply color of all drawables in the specified part and its child parts,
+    /// `None` removes the override.
+    #[inline]
+    pub fn set_part_multiply_color<K: PartKey>(&mut self, key: K, color: Option<[f32; 4]>) {
+        self.multiply_overrides.parts[self.moc.expect_part(key)] = color;
+    }
+
+    /// Overrides the screen color of all drawables in the specified part and its child parts,
+    /// `None` removes the override.
+    #[inline]
+    pub fn set_part_screen_color<K: PartKey>(&mut self, key: K, color: Option<[f32; 4]>) {
+        self.screen_overrides.parts[self.moc.expect_part(key)] = color;
+    }
+
+    /// Returns the multiply color the specified drawable should be rendered with.
+    ///
+    /// A drawable override takes precedence over the override of the closest part above the drawable,
+    /// which in turn takes precedence over the color computed by the core.
+    #[inline]
+    pub fn effective_multiply_color<K: DrawableKey>(&self, key: K) -> [f32; 4] {
+        let idx = self.moc.expect_drawable(key);
+        self.effective_color(
+            &self.multiply_overrides,
+            idx,
+            self.drawable_multiply_colors()[idx],
+        )
+    }
+
+    /// Returns the screen color the specified drawable should be rendered with.
+    ///
+    /// A drawable override takes precedence over the override of the closest part above the drawable,
+    /// which in turn takes precedence over the color computed by the core.
+    #[inline]
+    pub fn effective_screen_color<K: DrawableKey>(&self, key: K) -> [f32; 4] {
+        let idx = self.moc.expect_drawable(key);
+        self.effective_color(
+            &self.screen_overrides,
+            idx,
+            self.drawable_screen_colors()[idx],
+        )
+    }
+
+    fn effective_color(&self, overrides: &ColorOverrides, idx: usize, color: [f32; 4]) -> [f32; 4] {
+        if let Some(color) = overrides.drawables[idx] {
+            return color;
+        }
+        let mut part = self.drawable_parent_part_indices()[idx];
+        while let Some(idx) = part {
+            if let Some(color) = overrides.parts[idx] {
+                return color;
+            }
+            part = self.part_parent_indices()[idx];
+        }
+        color
+    }
+
     /// Returns the [ConstantFlags](./struct.ConstantFlags.html)
     #[inline]
     pub fn drawable_constant_flags(&self) -> &[ConstantFlags] {
@@ -266,6 +639,12 @@ impl Model {
         &self.moc
     }
 
+    /// Returns the shared [Moc](./struct.Moc.html) of this model.
+    #[inline]
+    pub(crate) fn shared_moc(&self) -> &Arc<Moc> {
+        &self.moc
+    }
+
     /// Returns the raw [csmModel](../cubism_core_sys/model/struct.csmModel.html) ptr.
     #[inline]
     pub fn as_ptr(&self) -> *const csmModel {
@@ -281,12 +660,18 @@ impl Model {
 
 //Constructors
 impl Model {
-    /// Creates a model instance from byte data
+    /// Creates a model instance from byte data.
+    ///
+    /// The data is validated by the core before it is used, data that isn't a moc, a moc that is newer than
+    /// what the linked core supports or a moc that fails the consistency check result in an error.
     pub fn from_bytes(data: &[u8]) -> Result<Self, CubismError> {
-        let mut moc = Moc::new(data)?;
+        Self::new(&Moc::from_bytes(data)?)
+    }
+
+    /// Creates a new model instance of `moc` with all parameters set to their default values.
+    pub fn new(moc: &Arc<Moc>) -> Result<Self, CubismError> {
         let model_mem = moc.init_new_model()?;
-        moc.init_ids(&model_mem)?;
-        Ok(Self::new_impl(Rc::new(moc), model_mem))
+        Ok(Self::new_impl(moc.clone(), model_mem))
     }
 
     /// Creates a model instance from a reader instance
@@ -301,31 +686,31 @@ impl Model {
     pub fn try_clone_from(&self) -> Result<Self, CubismError> {
         let moc = self.moc.clone();
         let model_mem = moc.init_new_model()?;
-        let model = Self::new_impl(moc, model_mem);
-        model.param_values.copy_from_slice(self.param_values);
-        model.part_opacities.copy_from_slice(self.part_opacities);
+        let mut model = Self::new_impl(moc, model_mem);
+        model
+            .parameter_values_mut()
+            .copy_from_slice(self.parameter_values());
+        model
+            .part_opacities_mut()
+            .copy_from_slice(self.part_opacities());
+        model.multiply_overrides = self.multiply_overrides.clone();
+        model.screen_overrides = self.screen_overrides.clone();
+        model.virtual_parameters = self.virtual_parameters.clone();
         Ok(model)
     }
 
-    pub(crate) fn new_impl(moc: Rc<Moc>, mut mem: AlignedMemory<core::csmModel>) -> Model {
-        unsafe {
-            let param_values = slice::from_raw_parts_mut(
-                core::csmGetParameterValues(mem.as_mut_ptr()),
-                moc.parameter_count(),
-            );
-            let part_opacities = slice::from_raw_parts_mut(
-                core::csmGetPartOpacities(mem.as_mut_ptr()),
-                moc.part_count(),
-            );
-            let drawable_count = core::csmGetDrawableCount(mem.as_mut_ptr()) as usize;
-
-            Model {
-                mem,
-                moc,
-                param_values,
-                part_opacities,
-                drawable_count,
-            }
+    pub(crate) fn new_impl(moc: Arc<Moc>, mem: AlignedMemory<core::csmModel>) -> Model {
+        let drawable_count = unsafe { core::csmGetDrawableCount(mem.as_ptr()) } as usize;
+        let multiply_overrides = ColorOverrides::new(drawable_count, moc.part_count());
+        let screen_overrides = multiply_overrides.clone();
+        Model {
+            mem,
+            moc,
+            drawable_count,
+            flags_need_reset: false,
+            multiply_overrides,
+            screen_overrides,
+            virtual_parameters: HashMap::new(),
         }
     }
 }
@@ -336,3 +721,473 @@ impl ops::Deref for Model {
         &self.moc
     }
 }
+
+#[cfg(all(test, feature = "mock-core"))]
+mod tests {
+    use super::{Moc, Model};
+    use core::mock::{MockMoc, MockPart};
+    use flags::DynamicFlags;
+    use mdl::{Aabb, DrawableIndex, ParameterIndex, ParameterType, PartIndex};
+    use std::thread;
+    use CubismError;
+
+    #[test]
+    fn model_from_mock() {
+        let model = Model::from_bytes(&MockMoc::sample().to_bytes()).unwrap();
+        assert_eq!(model.parameter_ids(), &["ParamAngleX", "ParamArmX"]);
+        assert_eq!(model.part_ids(), &["PartBody", "PartArm"]);
+        assert_eq!(model.drawable_ids(), &["ArtMesh0", "ArtMesh1", "ArtMesh2"]);
+        assert_eq!(model.parameter_values(), model.parameter_default());
+        assert_eq!(model.drawable_render_orders(), &[1, 2, 0]);
+        assert_eq!(model.drawable_masks(2), &[0]);
+        assert_eq!(model.drawable_indices(0), &[0, 1, 2, 2, 3, 0]);
+        let canvas = model.canvas_info();
+        assert_eq!(canvas.size, (100.0, 100.0));
+        assert_eq!(canvas.origin, (50.0, 50.0));
+        assert_eq!(canvas.pixels_per_unit, 100.0);
+    }
+
+    #[test]
+    fn model_from_invalid_bytes() {
+        let bytes = MockMoc::sample().to_bytes();
+        match Model::from_bytes(b"not a moc") {
+            Err(CubismError::InvalidMoc) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+        match Model::from_bytes(&bytes[..bytes.len() - 4]) {
+            Err(CubismError::InconsistentMoc) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+        let mut newer = MockMoc::sample();
+        newer.version += 1;
+        match Model::from_bytes(&newer.to_bytes()) {
+            Err(err @ CubismError::UnsupportedMocVersion { .. }) => {
+                assert_eq!(
+                    err.to_string(),
+                    format!(
+                        "unsupported moc version {}, the core supports versions up to {}",
+                        newer.version,
+                        newer.version - 1
+                    )
+                );
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn model_update() {
+        let mut model = Model::from_bytes(&MockMoc::sample().to_bytes()).unwrap();
+        let idx = model.parameter_index("ParamArmX").unwrap();
+        model.set_parameter_value(idx, 1.0);
+        model.set_part_opacity("PartBody", 0.0);
+        model.update();
+        assert_eq!(model.drawable_vertex_positions(1)[0], (1.0, 0.0));
+        assert_eq!(model.drawable_opacities()[0], 0.0);
+        assert!(!model.drawable_dynamic_flags()[0].contains(DynamicFlags::IS_VISIBLE));
+    }
+
+    #[test]
+    fn model_metadata() {
+        let mut desc = MockMoc::sample();
+        desc.parameters[0].repeat = true;
+        desc.parameters[1].ty = ::core::csmParameterType_BlendShape;
+        let model = Model::from_bytes(&desc.to_bytes()).unwrap();
+        assert_eq!(
+            model.parameter_types(),
+            &[ParameterType::Normal, ParameterType::BlendShape]
+        );
+        assert_eq!(model.parameter_key_values(0), &[-30.0, 0.0, 30.0]);
+        assert_eq!(model.parameter_repeats(), &[true, false]);
+        assert_eq!(model.part_parent_indices(), &[None, Some(0)]);
+        assert_eq!(
+            model.drawable_parent_part_indices(),
+            &[Some(0), Some(1), None]
+        );
+    }
+
+    #[test]
+    fn model_blend_colors() {
+        let mut desc = MockMoc::sample();
+        desc.drawables[1].multiply_color = [1.0, 0.5, 0.5, 1.0];
+        let mut model = Model::from_bytes(&desc.to_bytes()).unwrap();
+        assert_eq!(model.drawable_multiply_colors()[1], [1.0, 0.5, 0.5, 1.0]);
+        assert_eq!(model.effective_screen_color(0), [0.0, 0.0, 0.0, 1.0]);
+
+        let red = [1.0, 0.0, 0.0, 1.0];
+        let blue = [0.0, 0.0, 1.0, 1.0];
+        // PartArm is a child of PartBody so both ArtMesh0 and ArtMesh1 inherit the override
+        model.set_part_multiply_color(0, Some(red));
+        model.set_drawable_multiply_color(1, Some(blue));
+        assert_eq!(model.effective_multiply_color(0), red);
+        assert_eq!(model.effective_multiply_color(1), blue);
+        assert_eq!(model.effective_multiply_color(2), [1.0, 1.0, 1.0, 1.0]);
+        model.set_drawable_multiply_color(1, None);
+        assert_eq!(model.effective_multiply_color(1), red);
+    }
+
+    #[test]
+    fn models_from_moc() {
+        let moc = Moc::from_bytes(&MockMoc::sample().to_bytes()).unwrap();
+        assert_eq!(moc.parameter_ids(), &["ParamAngleX", "ParamArmX"]);
+        let mut first = Model::new(&moc).unwrap();
+        first.set_parameter_value(0, 10.0);
+        let second = Model::new(&moc).unwrap();
+        assert_eq!(second.parameter_values(), moc.parameter_default());
+        drop(first);
+        // the moc's data must outlive every model
+        assert_eq!(second.parameter_max(), &[30.0, 1.0]);
+    }
+
+    #[test]
+    fn models_on_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Moc>();
+        assert_send_sync::<Model>();
+
+        let moc = Moc::from_bytes(&MockMoc::sample().to_bytes()).unwrap();
+        let model = Model::new(&moc).unwrap();
+        let model = thread::spawn(move || {
+            let mut model = model;
+            model.set_parameter_value(1, -1.0);
+            model.update();
+            model
+        })
+        .join()
+        .unwrap();
+        assert_eq!(model.drawable_vertex_positions(1)[0], (-1.0, 0.0));
+
+        let handles: Vec<_> = (0..4)
+            .map(|i| {
+                let moc = moc.clone();
+                thread::spawn(move || {
+                    let mut model = Model::new(&moc).unwrap();
+                    model.set_parameter_value(1, i as f32 / 4.0);
+                    model.update();
+                    model.drawable_vertex_positions(1)[0]
+                })
+            })
+            .collect();
+        for (i, handle) in handles.into_iter().enumerate() {
+            assert_eq!(handle.join().unwrap(), (i as f32 / 4.0, 0.0));
+        }
+    }
+
+    #[test]
+    fn model_keys() {
+        let mut model = Model::from_bytes(&MockMoc::sample().to_bytes()).unwrap();
+        assert_eq!(model.parameter_index("ParamArmX"), Some(ParameterIndex(1)));
+        assert_eq!(
+            model.parameter_index(String::from("ParamArmX")),
+            Some(ParameterIndex(1))
+        );
+        assert_eq!(model.parameter_index(2), None);
+        assert_eq!(model.part_index("PartArm"), Some(PartIndex(1)));
+        assert_eq!(model.part_index("ParamArmX"), None);
+        assert_eq!(model.drawable_index("ArtMesh2"), Some(DrawableIndex(2)));
+
+        let arm = model.parameter_index("ParamArmX").unwrap();
+        model.set_parameter_value(arm, 0.25);
+        assert_eq!(model.parameter_value("ParamArmX"), 0.25);
+        model.set_parameter_value("ParamAngleX", 5.0);
+        assert_eq!(model.parameter_value(0), 5.0);
+        model.set_part_opacity(PartIndex(0), 0.5);
+        assert_eq!(model.part_opacity("PartBody"), 0.5);
+        assert_eq!(model.drawable_masks("ArtMesh2"), model.drawable_masks(2));
+    }
+
+    #[test]
+    #[should_panic]
+    fn model_unknown_key() {
+        let mut model = Model::from_bytes(&MockMoc::sample().to_bytes()).unwrap();
+        model.set_parameter_value("ParamDoesNotExist", 1.0);
+    }
+
+    #[test]
+    fn model_parameter_views() {
+        let mut desc = MockMoc::sample();
+        desc.parameters[0].repeat = true;
+        let mut model = Model::from_bytes(&desc.to_bytes()).unwrap();
+        {
+            let arm = model.parameter("ParamArmX").unwrap();
+            assert_eq!(arm.index(), ParameterIndex(1));
+            assert_eq!(arm.id(), "ParamArmX");
+            assert_eq!((arm.minimum(), arm.maximum()), (-1.0, 1.0));
+            assert_eq!(arm.default_value(), 0.5);
+            assert_eq!(arm.value(), 0.5);
+            assert_eq!(arm.parameter_type(), ParameterType::Normal);
+            assert_eq!(arm.key_values(), &[-1.0, 0.5, 1.0]);
+            assert!(!arm.repeats());
+        }
+        assert!(model.parameter("ParamDoesNotExist").is_none());
+
+        {
+            let mut arm = model.parameter_mut(1).unwrap();
+            arm.set_value(3.0);
+            assert_eq!(arm.value(), 1.0);
+            arm.set_value(-3.0);
+            assert_eq!(arm.value(), -1.0);
+            arm.reset_to_default();
+            assert_eq!(arm.value(), 0.5);
+        }
+        {
+            // ParamAngleX repeats in the range -30..30
+            let mut angle = model.parameter_mut("ParamAngleX").unwrap();
+            angle.set_value(40.0);
+            assert_eq!(angle.value(), -20.0);
+            angle.set_value(-35.0);
+            assert_eq!(angle.value(), 25.0);
+            angle.set_value(30.0);
+            assert_eq!(angle.value(), 30.0);
+        }
+
+        for mut param in model.parameters_mut() {
+            let max = param.maximum();
+            param.set_value(max);
+        }
+        let ids: Vec<_> = model.parameters().map(|param| param.id()).collect();
+        assert_eq!(ids, &["ParamAngleX", "ParamArmX"]);
+        assert_eq!(model.parameter_values(), &[30.0, 1.0]);
+        model.reset_parameters();
+        assert_eq!(model.parameter_values(), model.parameter_default());
+    }
+
+    #[test]
+    fn model_drawable_views() {
+        let mut model = Model::from_bytes(&MockMoc::sample().to_bytes()).unwrap();
+        model.set_part_opacity("PartArm", 0.0);
+        model.update();
+        {
+            let arm = model.drawable("ArtMesh1").unwrap();
+            assert_eq!(arm.index(), DrawableIndex(1));
+            assert_eq!(arm.id(), "ArtMesh1");
+            assert_eq!(arm.texture_index(), 1);
+            assert_eq!(arm.draw_order(), 600);
+            assert_eq!(arm.render_order(), 2);
+            assert_eq!(arm.opacity(), 0.0);
+            assert!(!arm.is_visible());
+            assert_eq!(arm.parent_part(), Some(PartIndex(1)));
+            assert_eq!(arm.vertex_positions(), model.drawable_vertex_positions(1));
+            assert_eq!(arm.vertex_uvs(), model.drawable_vertex_uvs(1));
+            assert_eq!(arm.indices(), &[0, 1, 2, 2, 3, 0]);
+            assert_eq!(model.drawable(2).unwrap().masks(), &[0]);
+            assert!(model.drawable(3).is_none());
+        }
+
+        let ids: Vec<_> = model.drawables().map(|drawable| drawable.id()).collect();
+        assert_eq!(ids, &["ArtMesh0", "ArtMesh1", "ArtMesh2"]);
+        let ids: Vec<_> = model
+            .drawables_in_render_order()
+            .map(|drawable| drawable.id())
+            .collect();
+        assert_eq!(ids, &["ArtMesh2", "ArtMesh0", "ArtMesh1"]);
+        assert_eq!(
+            model.drawables_in_render_order().next_back().unwrap().id(),
+            "ArtMesh1"
+        );
+    }
+
+    #[test]
+    fn model_part_tree() {
+        let mut desc = MockMoc::sample();
+        desc.parts.push(MockPart {
+            id: "PartHand".into(),
+            parent: 1,
+        });
+        desc.parts.push(MockPart {
+            id: "PartHat".into(),
+            parent: -1,
+        });
+        desc.drawables[2].part = 2;
+        let mut model = Model::from_bytes(&desc.to_bytes()).unwrap();
+        {
+            let tree = model.part_tree();
+            assert_eq!(tree.len(), 4);
+            assert_eq!(tree.roots(), &[PartIndex(0), PartIndex(3)]);
+            assert_eq!(tree.parent(PartIndex(2)), Some(PartIndex(1)));
+            assert_eq!(tree.children(PartIndex(0)), &[PartIndex(1)]);
+            let ancestors: Vec<_> = tree.ancestors(PartIndex(2)).collect();
+            assert_eq!(ancestors, &[PartIndex(1), PartIndex(0)]);
+            assert!(tree.is_in_subtree(PartIndex(2), PartIndex(0)));
+            assert!(!tree.is_in_subtree(PartIndex(0), PartIndex(2)));
+            let subtree: Vec<_> = tree.subtree(PartIndex(0)).collect();
+            assert_eq!(subtree, &[PartIndex(0), PartIndex(1), PartIndex(2)]);
+            assert_eq!(tree.drawables(PartIndex(1)), &[DrawableIndex(1)]);
+            assert_eq!(
+                tree.subtree_drawables(PartIndex(1)),
+                &[DrawableIndex(1), DrawableIndex(2)]
+            );
+            assert!(tree.orphan_drawables().is_empty());
+        }
+
+        model.set_part_opacity("PartBody", 0.5);
+        model.set_part_opacity("PartArm", 0.5);
+        assert_eq!(model.cascaded_part_opacity("PartHand"), 0.25);
+        model.set_subtree_visible("PartArm", false);
+        assert_eq!(model.part_opacities(), &[0.5, 0.0, 0.0, 1.0]);
+        model.set_subtree_opacity(0, 0.75);
+        assert_eq!(model.part_opacities(), &[0.75, 0.75, 0.75, 1.0]);
+    }
+
+    #[test]
+    fn model_weighted_parameters() {
+        let mut desc = MockMoc::sample();
+        desc.parameters[0].repeat = true;
+        let mut model = Model::from_bytes(&desc.to_bytes()).unwrap();
+        // ParamArmX ranges from -1 to 1 and defaults to 0.5
+        model.set_parameter_value_weighted("ParamArmX", 0.0, 0.5);
+        assert_eq!(model.parameter_value(1), 0.25);
+        model.set_parameter_value_weighted(1, 5.0, 0.5);
+        assert_eq!(model.parameter_value(1), 0.625);
+        model.set_parameter_value_weighted(1, 5.0, 1.0);
+        assert_eq!(model.parameter_value(1), 1.0);
+
+        model.set_parameter_value(1, 0.5);
+        model.add_parameter_value("ParamArmX", 0.5, 0.5);
+        assert_eq!(model.parameter_value(1), 0.75);
+        model.add_parameter_value(1, 1.0, 1.0);
+        assert_eq!(model.parameter_value(1), 1.0);
+
+        model.set_parameter_value(1, 0.5);
+        model.multiply_parameter_value("ParamArmX", 3.0, 0.5);
+        assert_eq!(model.parameter_value(1), 1.0);
+        model.multiply_parameter_value(1, 0.5, 1.0);
+        assert_eq!(model.parameter_value(1), 0.5);
+        model.multiply_parameter_value(1, 0.0, 0.0);
+        assert_eq!(model.parameter_value(1), 0.5);
+
+        // ParamAngleX repeats in the range -30..30
+        model.add_parameter_value("ParamAngleX", 50.0, 1.0);
+        assert_eq!(model.parameter_value(0), -10.0);
+    }
+
+    #[test]
+    fn model_virtual_parameters() {
+        let mut model = Model::from_bytes(&MockMoc::sample().to_bytes()).unwrap();
+        assert_eq!(model.virtual_parameter_value("ParamArmX"), Some(0.5));
+        assert_eq!(model.virtual_parameter_value("PartArm"), None);
+
+        model.set_virtual_parameter_value("ParamArmX", 5.0);
+        assert_eq!(model.parameter_value("ParamArmX"), 1.0);
+        model.set_virtual_parameter_value("PartArm", 5.0);
+        assert_eq!(model.virtual_parameter_value("PartArm"), Some(5.0));
+        let clone = model.try_clone_from().unwrap();
+        assert_eq!(clone.virtual_parameter_value("PartArm"), Some(5.0));
+    }
+
+    #[test]
+    fn model_dynamic_flags() {
+        let mut model = Model::from_bytes(&MockMoc::sample().to_bytes()).unwrap();
+        model.update();
+        // everything changed compared to the uninitialized model
+        assert_eq!(model.changed_drawables().count(), 3);
+        assert!(model.drawable_dynamic_flags()[0].contains(DynamicFlags::VERTEX_POSITIONS_CHANGED));
+
+        model.update();
+        assert_eq!(model.changed_drawables().next(), None);
+        assert!(model.drawable(0).unwrap().is_visible());
+
+        model.set_parameter_value("ParamArmX", 1.0);
+        model.update();
+        let changes: Vec<_> = model.changed_drawables().collect();
+        assert_eq!(
+            changes,
+            &[(DrawableIndex(1), DynamicFlags::VERTEX_POSITIONS_CHANGED)]
+        );
+
+        model.set_part_opacity("PartBody", 0.5);
+        model.update();
+        let changes: Vec<_> = model.changed_drawables().collect();
+        assert_eq!(
+            changes,
+            &[
+                (DrawableIndex(0), DynamicFlags::OPACITY_CHANGED),
+                (DrawableIndex(1), DynamicFlags::OPACITY_CHANGED),
+            ]
+        );
+        assert_eq!(
+            model.drawable(1).unwrap().changes(),
+            DynamicFlags::OPACITY_CHANGED
+        );
+        model.reset_dynamic_flags();
+        assert_eq!(model.changed_drawables().next(), None);
+        assert!(model.drawable(1).unwrap().is_visible());
+    }
+
+    #[test]
+    fn model_hit_test() {
+        let mut model = Model::from_bytes(&MockMoc::sample().to_bytes()).unwrap();
+        model.update();
+        // ArtMesh1 is moved to the right by ParamArmX which defaults to 0.5
+        assert_eq!(
+            model.drawable_bounds("ArtMesh1"),
+            Some(Aabb {
+                min: (0.5, 0.0),
+                max: (0.75, 0.25)
+            })
+        );
+        assert_eq!(
+            model.bounds(),
+            Some(Aabb {
+                min: (-1.0, -1.0),
+                max: (0.75, 0.5)
+            })
+        );
+
+        // ArtMesh1 is rendered in front of ArtMesh0 which is rendered in front of ArtMesh2
+        assert_eq!(model.hit_test((0.5, 0.1)), Some(DrawableIndex(1)));
+        assert_eq!(model.hit_test((-0.25, -0.25)), Some(DrawableIndex(0)));
+        assert_eq!(model.hit_test((0.9, 0.9)), None);
+        // ArtMesh2 is masked by ArtMesh0
+        assert!(!model.hit_test_drawable(2, (-0.75, -0.75)));
+        assert_eq!(model.hit_test((-0.75, -0.75)), None);
+
+        model.set_part_opacity("PartBody", 0.0);
+        model.update();
+        assert_eq!(model.hit_test((0.5, 0.1)), None);
+        assert_eq!(model.hit_test((-0.25, -0.25)), Some(DrawableIndex(2)));
+        assert_eq!(
+            model.bounds(),
+            Some(Aabb {
+                min: (-1.0, -1.0),
+                max: (0.0, 0.0)
+            })
+        );
+    }
+
+    #[test]
+    fn model_clone() {
+        let mut model = Model::from_bytes(&MockMoc::sample().to_bytes()).unwrap();
+        model.set_parameter_value(0, 10.0);
+        model.set_part_opacity(1, 0.5);
+        let mut clone = model.try_clone_from().unwrap();
+        assert_eq!(clone.parameter_values(), model.parameter_values());
+        assert_eq!(clone.part_opacities(), model.part_opacities());
+        // the clone has its own memory, so changing it must not affect the original
+        clone.parameter_values_mut()[0] = -10.0;
+        clone.update();
+        drop(model);
+        assert_eq!(clone.parameter_values()[0], -10.0);
+        assert_eq!(clone.drawable_opacities()[1], 0.5);
+    }
+
+    #[test]
+    fn model_outlives_moc() {
+        let moc = Moc::from_bytes(&MockMoc::sample().to_bytes()).unwrap();
+        let mut model = Model::new(&moc).unwrap();
+        drop(moc);
+        assert_eq!(model.parameter_ids()[1], "ParamArmX");
+        {
+            let values = model.parameter_values_mut();
+            values[1] = 1.0;
+            values[0] = values[1];
+        }
+        model.update();
+        assert_eq!(model.parameter_values(), &[1.0, 1.0]);
+        assert_eq!(model.drawable_vertex_positions(1)[0], (1.0, 0.0));
+        match Model::from_bytes(&[]) {
+            Err(CubismError::InvalidMoc) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
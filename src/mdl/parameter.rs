@@ -0,0 +1,320 @@
+//! Views of single parameters
+use std::iter::{ExactSizeIterator, FusedIterator};
+use std::slice;
+
+use super::index::ParameterIndex;
+use super::moc::{Moc, ParameterType};
+
+/// A view of a parameter of a [Model](./struct.Model.html), created with
+/// [Model::parameter](./struct.Model.html#method.parameter) or [Model::parameters](./struct.Model.html#method.parameters).
+#[derive(Clone, Copy, Debug)]
+pub struct Parameter<'a> {
+    moc: &'a Moc,
+    index: ParameterIndex,
+    value: &'a f32,
+}
+
+impl<'a> Parameter<'a> {
+    pub(crate) fn new(moc: &'a Moc, index: ParameterIndex, value: &'a f32) -> Self {
+        Parameter { moc, index, value }
+    }
+
+    /// Returns the index of this parameter.
+    #[inline]
+    pub fn index(&self) -> ParameterIndex {
+        self.index
+    }
+
+    /// Returns the id of this parameter.
+    #[inline]
+    pub fn id(&self) -> &'a str {
+        &self.moc.parameter_ids()[self.index.0]
+    }
+
+    /// Returns the current value of this parameter.
+    #[inline]
+    pub fn value(&self) -> f32 {
+        *self.value
+    }
+
+    /// Returns the minimum value of this parameter.
+    #[inline]
+    pub fn minimum(&self) -> f32 {
+        self.moc.parameter_min()[self.index.0]
+    }
+
+    /// Returns the maximum value of this parameter.
+    #[inline]
+    pub fn maximum(&self) -> f32 {
+        self.moc.parameter_max()[self.index.0]
+    }
+
+    /// Returns the default value of this parameter.
+    #[inline]
+    pub fn default_value(&self) -> f32 {
+        self.moc.parameter_default()[self.index.0]
+    }
+
+    /// Returns the type of this parameter.
+    #[inline]
+    pub fn parameter_type(&self) -> ParameterType {
+        self.moc.parameter_types()[self.index.0]
+    }
+
+    /// Returns the values at which this parameter has keyforms.
+    #[inline]
+    pub fn key_values(&self) -> &'a [f32] {
+        self.moc.parameter_key_values(self.index)
+    }
+
+    /// Returns whether this parameter wraps around instead of being clamped when it exceeds its range.
+    #[inline]
+    pub fn repeats(&self) -> bool {
+        self.moc.parameter_repeats()[self.index.0]
+    }
+
+    /// Returns `value` limited to the range of this parameter.
+    ///
+    /// Values of repeating parameters wrap around, all others are clamped.
+    pub fn limit(&self, value: f32) -> f32 {
+        let (min, max) = (self.minimum(), self.maximum());
+        if !self.repeats() {
+            return value.max(min).min(max);
+        }
+        // this matches the behaviour of the official framework
+        let size = max - min;
+        if value > max {
+            let over = (value - max) % size;
+            if over.is_nan() {
+                max
+            } else {
+                min + over
+            }
+        } else if value < min {
+            let over = (min - value) % size;
+            if over.is_nan() {
+                min
+            } else {
+                max - over
+            }
+        } else {
+            value
+        }
+    }
+}
+
+/// A mutable view of a parameter of a [Model](./struct.Model.html), created with
+/// [Model::parameter_mut](./struct.Model.html#method.parameter_mut) or [Model::parameters_mut](./struct.Model.html#method.parameters_mut).
+#[derive(Debug)]
+pub struct ParameterMut<'a> {
+    moc: &'a Moc,
+    index: ParameterIndex,
+    value: &'a mut f32,
+}
+
+impl<'a> ParameterMut<'a> {
+    pub(crate) fn new(moc: &'a Moc, index: ParameterIndex, value: &'a mut f32) -> Self {
+        ParameterMut { moc, index, value }
+    }
+
+    /// Returns an immutable view of this parameter.
+    #[inline]
+    pub fn as_parameter(&self) -> Parameter<'_> {
+        Parameter::new(self.moc, self.index, self.value)
+    }
+
+    /// Returns the index of this parameter.
+    #[inline]
+    pub fn index(&self) -> ParameterIndex {
+        self.index
+    }
+
+    /// Returns the id of this parameter.
+    #[inline]
+    pub fn id(&self) -> &'a str {
+        &self.moc.parameter_ids()[self.index.0]
+    }
+
+    /// Returns the current value of this parameter.
+    #[inline]
+    pub fn value(&self) -> f32 {
+        *self.value
+    }
+
+    /// Returns the minimum value of this parameter.
+    #[inline]
+    pub fn minimum(&self) -> f32 {
+        self.as_parameter().minimum()
+    }
+
+    /// Returns the maximum value of this parameter.
+    #[inline]
+    pub fn maximum(&self) -> f32 {
+        self.as_parameter().maximum()
+    }
+
+    /// Returns the default value of this parameter.
+    #[inline]
+    pub fn default_value(&self) -> f32 {
+        self.as_parameter().default_value()
+    }
+
+    /// Returns the type of this parameter.
+    #[inline]
+    pub fn parameter_type(&self) -> ParameterType {
+        self.as_parameter().parameter_type()
+    }
+
+    /// Returns the values at which this parameter has keyforms.
+    #[inline]
+    pub fn key_values(&self) -> &'a [f32] {
+        self.moc.parameter_key_values(self.index)
+    }
+
+    /// Returns whether this parameter wraps around instead of being clamped when it exceeds its range.
+    #[inline]
+    pub fn repeats(&self) -> bool {
+        self.as_parameter().repeats()
+    }
+
+    /// Sets the value of this parameter, see [limit](./struct.Parameter.html#method.limit) for how
+    /// values outside of the range are handled.
+    #[inline]
+    pub fn set_value(&mut self, value: f32) {
+        *self.value = self.as_parameter().limit(value);
+    }
+
+    /// Blends the value of this parameter towards `value` by `weight`, a weight of 1 replaces the value.
+    ///
+    /// `value` is limited to the range of the parameter before blending, like in the official framework's
+    /// `SetParameterValue`.
+    #[inline]
+    pub fn set_value_weighted(&mut self, value: f32, weight: f32) {
+        let value = self.as_parameter().limit(value);
+        *self.value = if weight == 1.0 {
+            value
+        } else {
+            *self.value * (1.0 - weight) + value * weight
+        };
+    }
+
+    /// Adds `value` scaled by `weight` to the value of this parameter, the result is limited to the range
+    /// of the parameter.
+    #[inline]
+    pub fn add_value(&mut self, value: f32, weight: f32) {
+        let value = *self.value + value * weight;
+        self.set_value(value);
+    }
+
+    /// Multiplies the value of this parameter with `value`, `weight` blends between a factor of 1 and `value`.
+    /// The result is limited to the range of the parameter.
+    #[inline]
+    pub fn multiply_value(&mut self, value: f32, weight: f32) {
+        let value = *self.value * (1.0 + (value - 1.0) * weight);
+        self.set_value(value);
+    }
+
+    /// Sets the value of this parameter without limiting it to its range.
+    #[inline]
+    pub fn set_value_unchecked(&mut self, value: f32) {
+        *self.value = value;
+    }
+
+    /// Resets this parameter to its default value.
+    #[inline]
+    pub fn reset_to_default(&mut self) {
+        *self.value = self.default_value();
+    }
+}
+
+/// An iterator over the parameters of a model, created with [Model::parameters](./struct.Model.html#method.parameters).
+#[derive(Clone, Debug)]
+pub struct Parameters<'a> {
+    moc: &'a Moc,
+    values: ::std::iter::Enumerate<slice::Iter<'a, f32>>,
+}
+
+impl<'a> Parameters<'a> {
+    pub(crate) fn new(moc: &'a Moc, values: &'a [f32]) -> Self {
+        Parameters {
+            moc,
+            values: values.iter().enumerate(),
+        }
+    }
+}
+
+impl<'a> Iterator for Parameters<'a> {
+    type Item = Parameter<'a>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let moc = self.moc;
+        self.values
+            .next()
+            .map(|(idx, value)| Parameter::new(moc, ParameterIndex(idx), value))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.values.size_hint()
+    }
+}
+
+impl<'a> DoubleEndedIterator for Parameters<'a> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let moc = self.moc;
+        self.values
+            .next_back()
+            .map(|(idx, value)| Parameter::new(moc, ParameterIndex(idx), value))
+    }
+}
+
+impl<'a> ExactSizeIterator for Parameters<'a> {}
+impl<'a> FusedIterator for Parameters<'a> {}
+
+/// A mutable iterator over the parameters of a model, created with [Model::parameters_mut](./struct.Model.html#method.parameters_mut).
+#[derive(Debug)]
+pub struct ParametersMut<'a> {
+    moc: &'a Moc,
+    values: ::std::iter::Enumerate<slice::IterMut<'a, f32>>,
+}
+
+impl<'a> ParametersMut<'a> {
+    pub(crate) fn new(moc: &'a Moc, values: &'a mut [f32]) -> Self {
+        ParametersMut {
+            moc,
+            values: values.iter_mut().enumerate(),
+        }
+    }
+}
+
+impl<'a> Iterator for ParametersMut<'a> {
+    type Item = ParameterMut<'a>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let moc = self.moc;
+        self.values
+            .next()
+            .map(|(idx, value)| ParameterMut::new(moc, ParameterIndex(idx), value))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.values.size_hint()
+    }
+}
+
+impl<'a> DoubleEndedIterator for ParametersMut<'a> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let moc = self.moc;
+        self.values
+            .next_back()
+            .map(|(idx, value)| ParameterMut::new(moc, ParameterIndex(idx), value))
+    }
+}
+
+impl<'a> ExactSizeIterator for ParametersMut<'a> {}
+impl<'a> FusedIterator for ParametersMut<'a> {}
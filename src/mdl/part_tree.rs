@@ -0,0 +1,156 @@
+//! The part hierarchy
+use std::iter::FusedIterator;
+
+use super::index::{DrawableIndex, PartIndex};
+
+/// The hierarchy of the parts of a moc, built from the parent part indices reported by the core.
+///
+/// Returned by [Moc::part_tree](./struct.Moc.html#method.part_tree), the opacity helpers that make use of it live on
+/// [Model](./struct.Model.html#method.set_subtree_opacity).
+#[derive(Clone, Debug, Default)]
+pub struct PartTree {
+    parents: Vec<Option<PartIndex>>,
+    children: Vec<Vec<PartIndex>>,
+    roots: Vec<PartIndex>,
+    drawables: Vec<Vec<DrawableIndex>>,
+    orphan_drawables: Vec<DrawableIndex>,
+}
+
+impl PartTree {
+    pub(crate) fn new(part_parents: &[Option<usize>], drawable_parents: &[Option<usize>]) -> Self {
+        let mut tree = PartTree {
+            parents: part_parents.iter().map(|p| p.map(PartIndex)).collect(),
+            children: vec![Vec::new(); part_parents.len()],
+            roots: Vec::new(),
+            drawables: vec![Vec::new(); part_parents.len()],
+            orphan_drawables: Vec::new(),
+        };
+        for (idx, parent) in part_parents.iter().enumerate() {
+            match *parent {
+                Some(parent) => tree.children[parent].push(PartIndex(idx)),
+                None => tree.roots.push(PartIndex(idx)),
+            }
+        }
+        for (idx, parent) in drawable_parents.iter().enumerate() {
+            match *parent {
+                Some(parent) => tree.drawables[parent].push(DrawableIndex(idx)),
+                None => tree.orphan_drawables.push(DrawableIndex(idx)),
+            }
+        }
+        tree
+    }
+
+    /// Returns the number of parts in this tree.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.parents.len()
+    }
+
+    /// Returns true if the moc has no parts.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.parents.is_empty()
+    }
+
+    /// Returns the parts that have no parent.
+    #[inline]
+    pub fn roots(&self) -> &[PartIndex] {
+        &self.roots
+    }
+
+    /// Returns the parent of `part` or `None` if it is a root part.
+    #[inline]
+    pub fn parent(&self, part: PartIndex) -> Option<PartIndex> {
+        self.parents[part.0]
+    }
+
+    /// Returns the direct children of `part`.
+    #[inline]
+    pub fn children(&self, part: PartIndex) -> &[PartIndex] {
+        &self.children[part.0]
+    }
+
+    /// Returns the drawables that belong directly to `part`.
+    #[inline]
+    pub fn drawables(&self, part: PartIndex) -> &[DrawableIndex] {
+        &self.drawables[part.0]
+    }
+
+    /// Returns the drawables that don't belong to any part.
+    #[inline]
+    pub fn orphan_drawables(&self) -> &[DrawableIndex] {
+        &self.orphan_drawables
+    }
+
+    /// Returns an iterator over the ancestors of `part`, starting with its parent.
+    #[inline]
+    pub fn ancestors(&self, part: PartIndex) -> Ancestors<'_> {
+        Ancestors {
+            tree: self,
+            next: self.parent(part),
+        }
+    }
+
+    /// Returns true if `ancestor` is `part` or one of its ancestors.
+    pub fn is_in_subtree(&self, part: PartIndex, ancestor: PartIndex) -> bool {
+        part == ancestor || self.ancestors(part).any(|p| p == ancestor)
+    }
+
+    /// Returns a depth-first iterator over `part` and all of its descendants, `part` is returned first.
+    #[inline]
+    pub fn subtree(&self, part: PartIndex) -> Subtree<'_> {
+        Subtree {
+            tree: self,
+            stack: vec![part],
+        }
+    }
+
+    /// Returns all drawables that belong to `part` or one of its descendants.
+    pub fn subtree_drawables(&self, part: PartIndex) -> Vec<DrawableIndex> {
+        self.subtree(part)
+            .flat_map(|part| self.drawables(part).iter().cloned())
+            .collect()
+    }
+}
+
+/// An iterator over the ancestors of a part, created with [PartTree::ancestors](./struct.PartTree.html#method.ancestors).
+#[derive(Clone, Debug)]
+pub struct Ancestors<'a> {
+    tree: &'a PartTree,
+    next: Option<PartIndex>,
+}
+
+impl<'a> Iterator for Ancestors<'a> {
+    type Item = PartIndex;
+
+    #[inline]
+    fn next(&mut self) -> Option<PartIndex> {
+        let part = self.next?;
+        self.next = self.tree.parent(part);
+        Some(part)
+    }
+}
+
+impl<'a> FusedIterator for Ancestors<'a> {}
+
+/// A depth-first iterator over a part and its descendants, created with [PartTree::subtree](./struct.PartTree.html#method.subtree).
+#[derive(Clone, Debug)]
+pub struct Subtree<'a> {
+    tree: &'a PartTree,
+    stack: Vec<PartIndex>,
+}
+
+impl<'a> Iterator for Subtree<'a> {
+    type Item = PartIndex;
+
+    #[inline]
+    fn next(&mut self) -> Option<PartIndex> {
+        let part = self.stack.pop()?;
+        // reversed so that the children are visited in index order
+        self.stack
+            .extend(self.tree.children(part).iter().rev().cloned());
+        Some(part)
+    }
+}
+
+impl<'a> FusedIterator for Subtree<'a> {}
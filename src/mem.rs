@@ -21,6 +21,9 @@ impl<T> fmt::Debug for AlignedMemory<T> {
 
 impl<T> AlignedMemory<T> {
     pub fn new(size: usize) -> Result<Self> {
+        if size == 0 {
+            return Err("Cannot allocate an empty block".into());
+        }
         let layout = Layout::from_size_align(size, mem::align_of::<T>()).unwrap();
         let ptr = unsafe { alloc::alloc(layout) as *mut T };
         if ptr.is_null() {
@@ -43,6 +46,10 @@ impl<T> AlignedMemory<T> {
     }
 }
 
+// AlignedMemory uniquely owns its allocation just like a Box does
+unsafe impl<T: Send> Send for AlignedMemory<T> {}
+unsafe impl<T: Sync> Sync for AlignedMemory<T> {}
+
 impl<T> Drop for AlignedMemory<T> {
     fn drop(&mut self) {
         unsafe { alloc::dealloc(self.ptr as *mut u8, self.layout) };
@@ -0,0 +1,925 @@
+//! The physics3.json file and the pendulum simulation of the official framework
+use std::f32::consts::PI;
+use std::fs::File;
+use std::io::Read;
+use std::ops::{Add, Mul, Sub};
+use std::path::Path;
+use std::ptr;
+use std::sync::{Arc, Weak};
+
+use serde_json;
+
+use mdl::{Moc, Model};
+use {CubismError, Result};
+
+const AIR_RESISTANCE: f32 = 5.0;
+const MAXIMUM_WEIGHT: f32 = 100.0;
+const MOVEMENT_THRESHOLD: f32 = 0.001;
+const MAX_DELTA_TIME: f32 = 5.0;
+const DEFAULT_FPS: f32 = 60.0;
+
+/// What an input reads from or an output writes to a pendulum.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+pub enum PhysicsType {
+    /// The horizontal translation.
+    X,
+    /// The vertical translation.
+    Y,
+    /// The angle.
+    Angle,
+}
+
+/// A parameter driving a [PhysicsSetting](./struct.PhysicsSetting.html).
+#[derive(Clone, Debug, PartialEq)]
+pub struct PhysicsInput {
+    /// The id of the parameter.
+    pub id: String,
+    /// The weight of the input in percent.
+    pub weight: f32,
+    /// What the parameter moves.
+    pub kind: PhysicsType,
+    /// Whether the parameter is inverted.
+    pub reflect: bool,
+}
+
+/// A parameter driven by a [PhysicsSetting](./struct.PhysicsSetting.html).
+#[derive(Clone, Debug, PartialEq)]
+pub struct PhysicsOutput {
+    /// The id of the parameter.
+    pub id: String,
+    /// The index of the particle the output is read from, always at least 1.
+    pub vertex_index: usize,
+    /// The factor the output is scaled with.
+    pub scale: f32,
+    /// The weight of the output in percent.
+    pub weight: f32,
+    /// What is read from the particle.
+    pub kind: PhysicsType,
+    /// Whether the output is inverted.
+    pub reflect: bool,
+}
+
+/// A particle of a pendulum.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct PhysicsParticle {
+    /// How much of its velocity the particle keeps.
+    pub mobility: f32,
+    /// How fast the particle reacts.
+    pub delay: f32,
+    /// The factor gravity is scaled with for this particle.
+    pub acceleration: f32,
+    /// The distance to the previous particle.
+    pub radius: f32,
+}
+
+/// The range inputs are normalized to.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct PhysicsNormalization {
+    /// The minimum value.
+    pub minimum: f32,
+    /// The value the middle of a parameter's range maps to.
+    pub default: f32,
+    /// The maximum value.
+    pub maximum: f32,
+}
+
+/// A single pendulum with the parameters driving it and the parameters it drives.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PhysicsSetting {
+    /// The id of this setting.
+    pub id: String,
+    /// The inputs of this setting.
+    pub inputs: Vec<PhysicsInput>,
+    /// The outputs of this setting.
+    pub outputs: Vec<PhysicsOutput>,
+    /// The particles of the pendulum, the first one is its root.
+    pub particles: Vec<PhysicsParticle>,
+    /// The range translation inputs are normalized to.
+    pub normalization_position: PhysicsNormalization,
+    /// The range angle inputs are normalized to.
+    pub normalization_angle: PhysicsNormalization,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Vec2 {
+    x: f32,
+    y: f32,
+}
+
+impl Vec2 {
+    fn new(x: f32, y: f32) -> Self {
+        Vec2 { x, y }
+    }
+
+    fn normalized(self) -> Self {
+        let length = (self.x * self.x + self.y * self.y).sqrt();
+        if length == 0.0 {
+            self
+        } else {
+            Vec2::new(self.x / length, self.y / length)
+        }
+    }
+}
+
+impl Add for Vec2 {
+    type Output = Vec2;
+
+    fn add(self, other: Vec2) -> Vec2 {
+        Vec2::new(self.x + other.x, self.y + other.y)
+    }
+}
+
+impl Sub for Vec2 {
+    type Output = Vec2;
+
+    fn sub(self, other: Vec2) -> Vec2 {
+        Vec2::new(self.x - other.x, self.y - other.y)
+    }
+}
+
+impl Mul<f32> for Vec2 {
+    type Output = Vec2;
+
+    fn mul(self, factor: f32) -> Vec2 {
+        Vec2::new(self.x * factor, self.y * factor)
+    }
+}
+
+// the indices of the parameters of a setting's inputs and outputs in the model
+#[derive(Clone, Debug)]
+struct SettingIndices {
+    inputs: Vec<Option<usize>>,
+    outputs: Vec<Option<usize>>,
+}
+
+impl SettingIndices {
+    fn resolve(setting: &PhysicsSetting, moc: &Moc) -> Self {
+        let index = |id: &str| moc.parameter_index(id).map(|idx| idx.0);
+        SettingIndices {
+            inputs: setting
+                .inputs
+                .iter()
+                .map(|input| index(&input.id))
+                .collect(),
+            outputs: setting
+                .outputs
+                .iter()
+                .map(|output| index(&output.id))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Particle {
+    position: Vec2,
+    last_position: Vec2,
+    last_gravity: Vec2,
+    velocity: Vec2,
+}
+
+/// The physics of a model loaded from a physics3.json file.
+///
+/// Every [evaluate](#method.evaluate) advances the simulation in fixed steps of `1 / fps` seconds,
+/// interpolating the inputs over the steps and the outputs between the last two steps, so the results
+/// only depend on the total time passed and not on how it is split up.
+#[derive(Clone, Debug)]
+pub struct Physics {
+    settings: Vec<PhysicsSetting>,
+    /// The direction of gravity used for the angle of the first particle of a pendulum, (0, -1) by
+    /// default.
+    ///
+    /// Like the framework's options this doesn't come from the file, the `EffectiveForces` of a
+    /// physics3.json file are ignored.
+    pub gravity: (f32, f32),
+    /// The wind acting on all particles, (0, 0) by default.
+    pub wind: (f32, f32),
+    /// The number of simulation steps per second, 60 if the file doesn't specify it.
+    pub fps: f32,
+    strands: Vec<Vec<Particle>>,
+    previous_outputs: Vec<Vec<f32>>,
+    current_outputs: Vec<Vec<f32>>,
+    parameter_caches: Vec<f32>,
+    parameter_input_caches: Vec<f32>,
+    indices: Vec<SettingIndices>,
+    // the moc the indices were resolved for
+    moc: Weak<Moc>,
+    remaining_time: f32,
+}
+
+impl Physics {
+    /// Loads the physics3.json file at `path`.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_reader(&mut File::open(path)?)
+    }
+
+    /// Parses the physics from a reader instance.
+    pub fn from_reader<R: Read>(reader: &mut R) -> Result<Self> {
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf)?;
+        Self::from_slice(&buf)
+    }
+
+    /// Parses the physics from json data.
+    pub fn from_slice(data: &[u8]) -> Result<Self> {
+        let json: Physics3Json = serde_json::from_slice(data)?;
+        if json.version != 3 {
+            return Err(invalid(format!(
+                "unsupported physics3.json version {}",
+                json.version
+            )));
+        }
+        let settings = json
+            .physics_settings
+            .into_iter()
+            .map(PhysicsSetting::from_json)
+            .collect::<Result<Vec<_>>>()?;
+        let fps = json.meta.fps.unwrap_or(DEFAULT_FPS);
+        if !(fps > 0.0 && fps.is_finite()) {
+            return Err(invalid(format!("invalid fps {}", fps)));
+        }
+        let mut physics = Physics {
+            gravity: (0.0, -1.0),
+            wind: (0.0, 0.0),
+            fps,
+            strands: Vec::new(),
+            previous_outputs: Vec::new(),
+            current_outputs: Vec::new(),
+            parameter_caches: Vec::new(),
+            parameter_input_caches: Vec::new(),
+            indices: Vec::new(),
+            moc: Weak::new(),
+            remaining_time: 0.0,
+            settings,
+        };
+        physics.reset();
+        Ok(physics)
+    }
+
+    /// Returns the pendulums of these physics.
+    #[inline]
+    pub fn settings(&self) -> &[PhysicsSetting] {
+        &self.settings
+    }
+
+    /// Puts all pendulums back into their resting position.
+    pub fn reset(&mut self) {
+        self.strands = self
+            .settings
+            .iter()
+            .map(|setting| {
+                let mut position = Vec2::default();
+                setting
+                    .particles
+                    .iter()
+                    .enumerate()
+                    .map(|(i, particle)| {
+                        if i > 0 {
+                            position.y += particle.radius;
+                        }
+                        Particle {
+                            position,
+                            last_position: position,
+                            last_gravity: Vec2::new(0.0, 1.0),
+                            velocity: Vec2::default(),
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        self.previous_outputs = self
+            .settings
+            .iter()
+            .map(|setting| vec![0.0; setting.outputs.len()])
+            .collect();
+        self.current_outputs = self.previous_outputs.clone();
+        self.parameter_caches.clear();
+        self.parameter_input_caches.clear();
+        self.indices.clear();
+        self.moc = Weak::new();
+        self.remaining_time = 0.0;
+    }
+
+    /// Advances the simulation by `delta` seconds, reading the inputs from and writing the outputs to `model`.
+    ///
+    /// Outputs are blended with the values already in the model by their weight. More than 5 seconds of
+    /// accumulated time are dropped instead of being simulated.
+    pub fn evaluate(&mut self, model: &mut Model, delta: f32) {
+        if delta <= 0.0 {
+            return;
+        }
+        self.remaining_time += delta;
+        if self.remaining_time > MAX_DELTA_TIME {
+            self.remaining_time = 0.0;
+        }
+        let step = 1.0 / self.fps;
+        // the weak reference keeps the address of the moc from being reused
+        let bound = ptr::eq(self.moc.as_ptr(), model.moc());
+        if !bound {
+            self.moc = Arc::downgrade(model.shared_moc());
+        }
+        let (moc, values) = model.split_parameter_values_mut();
+        if !bound {
+            self.parameter_input_caches = values.to_vec();
+            self.parameter_caches = values.to_vec();
+            self.indices = self
+                .settings
+                .iter()
+                .map(|setting| SettingIndices::resolve(setting, moc))
+                .collect();
+        }
+
+        while self.remaining_time >= step {
+            self.previous_outputs.clone_from(&self.current_outputs);
+            // the inputs are interpolated between the last step and the current values
+            let input_weight = step / self.remaining_time;
+            for ((cache, input_cache), &value) in self
+                .parameter_caches
+                .iter_mut()
+                .zip(&mut self.parameter_input_caches)
+                .zip(values.iter())
+            {
+                *cache = *input_cache * (1.0 - input_weight) + value * input_weight;
+                *input_cache = *cache;
+            }
+            for (s, setting) in self.settings.iter().enumerate() {
+                let strand = &mut self.strands[s];
+                let caches = &mut self.parameter_caches;
+                let indices = &self.indices[s];
+                let (translation, angle) = setting.read_inputs(moc, &indices.inputs, caches);
+                update_particles(
+                    strand,
+                    &setting.particles,
+                    translation,
+                    angle,
+                    Vec2::new(self.wind.0, self.wind.1),
+                    MOVEMENT_THRESHOLD * setting.normalization_position.maximum,
+                    step,
+                );
+                let gravity = Vec2::new(self.gravity.0, self.gravity.1);
+                for (o, output) in setting.outputs.iter().enumerate() {
+                    let value = output.read(strand, gravity);
+                    self.current_outputs[s][o] = value;
+                    if let Some(idx) = indices.outputs[o] {
+                        output.write(moc, idx, &mut caches[idx], value);
+                    }
+                }
+            }
+            self.remaining_time -= step;
+        }
+
+        let alpha = self.remaining_time / step;
+        for (s, setting) in self.settings.iter().enumerate() {
+            for (o, output) in setting.outputs.iter().enumerate() {
+                if let Some(idx) = self.indices[s].outputs[o] {
+                    let value = self.previous_outputs[s][o] * (1.0 - alpha)
+                        + self.current_outputs[s][o] * alpha;
+                    output.write(moc, idx, &mut values[idx], value);
+                }
+            }
+        }
+    }
+}
+
+impl PhysicsSetting {
+    fn from_json(json: Setting3Json) -> Result<Self> {
+        if json.vertices.is_empty() {
+            return Err(invalid(format!(
+                "physics setting {} has no vertices",
+                json.id
+            )));
+        }
+        let inputs = json
+            .input
+            .into_iter()
+            .map(|input| {
+                Ok(PhysicsInput {
+                    id: input.source.parameter_id()?,
+                    weight: input.weight,
+                    kind: input.kind,
+                    reflect: input.reflect,
+                })
+            })
+            .collect::<Result<_>>()?;
+        let vertex_count = json.vertices.len();
+        let id = &json.id;
+        let outputs = json
+            .output
+            .into_iter()
+            .map(|output| {
+                if output.vertex_index < 1 || output.vertex_index >= vertex_count {
+                    return Err(invalid(format!(
+                        "invalid vertex index {} in physics setting {}",
+                        output.vertex_index, id
+                    )));
+                }
+                Ok(PhysicsOutput {
+                    id: output.destination.parameter_id()?,
+                    vertex_index: output.vertex_index,
+                    scale: output.scale,
+                    weight: output.weight,
+                    kind: output.kind,
+                    reflect: output.reflect,
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(PhysicsSetting {
+            id: json.id,
+            inputs,
+            outputs,
+            particles: json.vertices,
+            normalization_position: json.normalization.position,
+            normalization_angle: json.normalization.angle,
+        })
+    }
+
+    fn read_inputs(&self, moc: &Moc, indices: &[Option<usize>], values: &[f32]) -> (Vec2, f32) {
+        let mut translation = Vec2::default();
+        let mut angle = 0.0;
+        for (input, &idx) in self.inputs.iter().zip(indices) {
+            let idx = match idx {
+                Some(idx) => idx,
+                None => continue,
+            };
+            let (normalization, target) = match input.kind {
+                PhysicsType::X => (&self.normalization_position, &mut translation.x),
+                PhysicsType::Y => (&self.normalization_position, &mut translation.y),
+                PhysicsType::Angle => (&self.normalization_angle, &mut angle),
+            };
+            *target += normalize_parameter_value(
+                values[idx],
+                moc.parameter_min()[idx],
+                moc.parameter_max()[idx],
+                normalization,
+                input.reflect,
+            ) * (input.weight / MAXIMUM_WEIGHT);
+        }
+        let (sin, cos) = (-angle).to_radians().sin_cos();
+        // like the framework, the rotated x is used to rotate y
+        translation.x = translation.x * cos - translation.y * sin;
+        translation.y = translation.x * sin + translation.y * cos;
+        (translation, angle)
+    }
+}
+
+impl PhysicsOutput {
+    fn read(&self, strand: &[Particle], gravity: Vec2) -> f32 {
+        let i = self.vertex_index;
+        let translation = strand[i].position - strand[i - 1].position;
+        let value = match self.kind {
+            PhysicsType::X => translation.x,
+            PhysicsType::Y => translation.y,
+            PhysicsType::Angle => {
+                let parent = if i >= 2 {
+                    strand[i - 1].position - strand[i - 2].position
+                } else {
+                    gravity * -1.0
+                };
+                direction_to_radian(parent, translation)
+            }
+        };
+        if self.reflect {
+            -value
+        } else {
+            value
+        }
+    }
+
+    fn write(&self, moc: &Moc, idx: usize, param: &mut f32, value: f32) {
+        let value = (value * self.scale)
+            .max(moc.parameter_min()[idx])
+            .min(moc.parameter_max()[idx]);
+        let weight = self.weight / MAXIMUM_WEIGHT;
+        if weight >= 1.0 {
+            *param = value;
+        } else {
+            *param = *param * (1.0 - weight) + value * weight;
+        }
+    }
+}
+
+fn update_particles(
+    strand: &mut [Particle],
+    particles: &[PhysicsParticle],
+    translation: Vec2,
+    angle: f32,
+    wind: Vec2,
+    threshold: f32,
+    delta: f32,
+) {
+    strand[0].position = translation;
+    let (sin, cos) = angle.to_radians().sin_cos();
+    let gravity = Vec2::new(sin, cos).normalized();
+    for i in 1..strand.len() {
+        let particle = &particles[i];
+        let force = gravity * particle.acceleration + wind;
+        let parent = strand[i - 1].position;
+        let current = &mut strand[i];
+        current.last_position = current.position;
+        let delay = particle.delay * delta * 30.0;
+
+        let mut direction = current.position - parent;
+        let radian = direction_to_radian(current.last_gravity, gravity) / AIR_RESISTANCE;
+        let (sin, cos) = radian.sin_cos();
+        // like the framework, the rotated x is used to rotate y
+        direction.x = cos * direction.x - direction.y * sin;
+        direction.y = sin * direction.x + direction.y * cos;
+
+        current.position = parent + direction;
+        current.position = current.position + current.velocity * delay + force * delay * delay;
+        let direction = (current.position - parent).normalized();
+        current.position = parent + direction * particle.radius;
+        if current.position.x.abs() < threshold {
+            current.position.x = 0.0;
+        }
+        if delay != 0.0 {
+            current.velocity =
+                (current.position - current.last_position) * (particle.mobility / delay);
+        }
+        current.last_gravity = gravity;
+    }
+}
+
+fn direction_to_radian(from: Vec2, to: Vec2) -> f32 {
+    let mut radian = to.y.atan2(to.x) - from.y.atan2(from.x);
+    while radian < -PI {
+        radian += PI * 2.0;
+    }
+    while radian > PI {
+        radian -= PI * 2.0;
+    }
+    radian
+}
+
+// maps a parameter value to the normalization range, the middle of the parameter's range to its default
+fn normalize_parameter_value(
+    value: f32,
+    minimum: f32,
+    maximum: f32,
+    normalization: &PhysicsNormalization,
+    reflect: bool,
+) -> f32 {
+    let (minimum, maximum) = (minimum.min(maximum), minimum.max(maximum));
+    let value = value.max(minimum).min(maximum);
+    let norm_minimum = normalization.minimum.min(normalization.maximum);
+    let norm_maximum = normalization.minimum.max(normalization.maximum);
+    let norm_middle = normalization.default;
+    let middle = minimum + (maximum - minimum) / 2.0;
+    let offset = value - middle;
+
+    let (norm_limit, limit) = if offset > 0.0 {
+        (norm_maximum, maximum)
+    } else if offset < 0.0 {
+        (norm_minimum, minimum)
+    } else {
+        return if reflect { norm_middle } else { -norm_middle };
+    };
+    let length = limit - middle;
+    let result = if length != 0.0 {
+        offset * ((norm_limit - norm_middle) / length) + norm_middle
+    } else {
+        0.0
+    };
+    if reflect {
+        result
+    } else {
+        -result
+    }
+}
+
+fn invalid(msg: String) -> CubismError {
+    CubismError::InvalidJson(msg)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Physics3Json {
+    version: u32,
+    meta: Meta3Json,
+    physics_settings: Vec<Setting3Json>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Meta3Json {
+    #[serde(default)]
+    fps: Option<f32>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Setting3Json {
+    id: String,
+    #[serde(default)]
+    input: Vec<Input3Json>,
+    #[serde(default)]
+    output: Vec<Output3Json>,
+    vertices: Vec<PhysicsParticle>,
+    normalization: Normalization3Json,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Target3Json {
+    target: String,
+    id: String,
+}
+
+impl Target3Json {
+    fn parameter_id(self) -> Result<String> {
+        if self.target != "Parameter" {
+            return Err(invalid(format!(
+                "unsupported physics target {}",
+                self.target
+            )));
+        }
+        Ok(self.id)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Input3Json {
+    source: Target3Json,
+    weight: f32,
+    #[serde(rename = "Type")]
+    kind: PhysicsType,
+    #[serde(default)]
+    reflect: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Output3Json {
+    destination: Target3Json,
+    vertex_index: usize,
+    scale: f32,
+    weight: f32,
+    #[serde(rename = "Type")]
+    kind: PhysicsType,
+    #[serde(default)]
+    reflect: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Normalization3Json {
+    position: PhysicsNormalization,
+    angle: PhysicsNormalization,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Physics, PhysicsType};
+    use CubismError;
+
+    const PHYSICS3: &str = r#"{
+        "Version": 3,
+        "Meta": {
+            "PhysicsSettingCount": 1,
+            "TotalInputCount": 2,
+            "TotalOutputCount": 2,
+            "VertexCount": 3,
+            "Fps": 30.0,
+            "EffectiveForces": {"Gravity": {"X": 0, "Y": -1}, "Wind": {"X": 1, "Y": 0}},
+            "PhysicsDictionary": [{"Id": "PhysicsSetting1", "Name": "Arm"}]
+        },
+        "PhysicsSettings": [{
+            "Id": "PhysicsSetting1",
+            "Input": [
+                {"Source": {"Target": "Parameter", "Id": "ParamAngleX"}, "Weight": 20, "Type": "X", "Reflect": false},
+                {"Source": {"Target": "Parameter", "Id": "ParamAngleX"}, "Weight": 30, "Type": "Angle", "Reflect": false}
+            ],
+            "Output": [
+                {"Destination": {"Target": "Parameter", "Id": "ParamArmX"}, "VertexIndex": 2,
+                    "Scale": 1.5, "Weight": 100, "Type": "Angle", "Reflect": false},
+                {"Destination": {"Target": "Parameter", "Id": "ParamMissing"}, "VertexIndex": 1,
+                    "Scale": 1, "Weight": 100, "Type": "X", "Reflect": true}
+            ],
+            "Vertices": [
+                {"Position": {"X": 0, "Y": 0}, "Mobility": 1, "Delay": 1, "Acceleration": 1, "Radius": 0},
+                {"Position": {"X": 0, "Y": 3}, "Mobility": 0.95, "Delay": 0.9, "Acceleration": 1.5, "Radius": 3},
+                {"Position": {"X": 0, "Y": 8}, "Mobility": 0.9, "Delay": 0.8, "Acceleration": 2, "Radius": 5}
+            ],
+            "Normalization": {
+                "Position": {"Minimum": -10, "Default": 0, "Maximum": 10},
+                "Angle": {"Minimum": -10, "Default": 0, "Maximum": 10}
+            }
+        }]
+    }"#;
+
+    #[test]
+    fn physics_from_json() {
+        let physics = Physics::from_slice(PHYSICS3.as_bytes()).unwrap();
+        assert_eq!(physics.fps, 30.0);
+        // the forces of the file are ignored like in the framework
+        assert_eq!(physics.gravity, (0.0, -1.0));
+        assert_eq!(physics.wind, (0.0, 0.0));
+        let setting = &physics.settings()[0];
+        assert_eq!(setting.inputs.len(), 2);
+        assert_eq!(setting.inputs[1].kind, PhysicsType::Angle);
+        assert_eq!(setting.outputs[0].vertex_index, 2);
+        assert!(setting.outputs[1].reflect);
+        assert_eq!(setting.particles[2].radius, 5.0);
+        assert_eq!(setting.normalization_angle.maximum, 10.0);
+
+        let without_fps = PHYSICS3.replace(r#""Fps": 30.0,"#, "");
+        assert_eq!(
+            Physics::from_slice(without_fps.as_bytes()).unwrap().fps,
+            60.0
+        );
+        let invalid = [
+            PHYSICS3.replace(r#""Version": 3"#, r#""Version": 2"#),
+            PHYSICS3.replace(r#""VertexIndex": 2"#, r#""VertexIndex": 3"#),
+            PHYSICS3.replace(r#""Fps": 30.0"#, r#""Fps": 0"#),
+        ];
+        for json in &invalid {
+            match Physics::from_slice(json.as_bytes()) {
+                Err(CubismError::InvalidJson(_)) => (),
+                other => panic!("unexpected result {:?}", other),
+            }
+        }
+    }
+
+    #[cfg(feature = "mock-core")]
+    #[test]
+    fn pendulum_rest() {
+        use core::mock::{MockMoc, MockParameter};
+        use mdl::Model;
+
+        // a pendulum held at a constant input comes to rest hanging along the tilted gravity:
+        // ParamAngleX at 30 tilts it by 30% of 10 degrees and the angle of the first particle
+        // is scaled by 1.5, the translation input only moves the root and leaves no lasting effect
+        let rest = |angle: f32| (angle / 30.0 * 3.0).to_radians() * 1.5;
+        let pendulum = PHYSICS3.replace(r#""VertexIndex": 2,"#, r#""VertexIndex": 1,"#);
+        let mut model = Model::from_bytes(&MockMoc::sample().to_bytes()).unwrap();
+        let mut physics = Physics::from_slice(pendulum.as_bytes()).unwrap();
+        for &angle in &[30.0, -10.0, 0.0] {
+            model.set_parameter_value("ParamAngleX", angle);
+            physics.evaluate(&mut model, 1.0 / 60.0);
+            // it lags behind the input
+            assert!((model.parameter_value("ParamArmX") - rest(angle)).abs() > 0.01);
+            for _ in 0..180 {
+                physics.evaluate(&mut model, 1.0 / 60.0);
+            }
+            let result = model.parameter_value("ParamArmX");
+            assert!(
+                (result - rest(angle)).abs() < 1e-4,
+                "{} != {} at rest for {}",
+                result,
+                rest(angle),
+                angle
+            );
+        }
+
+        // models of other mocs, one with the same parameters in a different order and one with more
+        // parameters
+        let mut more = MockMoc::sample();
+        more.parameters
+            .insert(0, MockParameter::new("ParamOther", 0.0, 1.0, 0.0));
+        let mut swapped = MockMoc::sample();
+        swapped.parameters.swap(0, 1);
+        for desc in &[swapped, more] {
+            let mut other_model = Model::from_bytes(&desc.to_bytes()).unwrap();
+            let defaults = other_model.parameter_values().to_vec();
+            other_model.set_parameter_value("ParamAngleX", 30.0);
+            for _ in 0..240 {
+                physics.evaluate(&mut other_model, 1.0 / 60.0);
+            }
+            let idx = other_model.parameter_index("ParamArmX").unwrap().0;
+            assert!((other_model.parameter_value(idx) - rest(30.0)).abs() < 1e-4);
+            let angle = other_model.parameter_index("ParamAngleX").unwrap().0;
+            for (i, (&value, &default)) in other_model
+                .parameter_values()
+                .iter()
+                .zip(&defaults)
+                .enumerate()
+            {
+                if i != idx && i != angle {
+                    assert_eq!(value, default);
+                }
+            }
+            assert_eq!(other_model.parameter_value(angle), 30.0);
+        }
+    }
+
+    // ParamArmX and ParamSway after every evaluate of `reference_output`, computed with a line by line
+    // port of the framework's CubismPhysics.cpp (Evaluate, UpdateParticles and Interpolate) in double
+    // precision
+    #[cfg(feature = "mock-core")]
+    const REFERENCE: &[(f32, f32)] = &[
+        (0.000000, 0.000000),
+        (0.009374, -0.024082),
+        (0.121863, -0.313062),
+        (0.276535, -0.710409),
+        (0.304741, -0.956174),
+        (0.164201, -0.743681),
+        (-0.029040, -0.451503),
+        (-0.304052, 0.066327),
+        (-0.387240, 0.237448),
+        (-0.470261, 0.437451),
+        (-0.512694, 0.708074),
+        (-0.518541, 0.789867),
+        (-0.465313, 0.817520),
+        (-0.347294, 0.861676),
+        (-0.281686, 0.827127),
+        (-0.191475, 0.779622),
+        (-0.179132, 1.067118),
+        (-0.245734, 1.322688),
+        (-0.337311, 1.674097),
+        (0.101744, 1.204688),
+        (0.296054, 0.961980),
+        (0.515927, 0.618525),
+        (0.856976, -0.124022),
+        (0.940928, -0.355331),
+        (0.961007, -0.599215),
+        (0.966701, -1.058978),
+        (0.894207, -1.115924),
+        (0.794527, -1.194224),
+        (0.521041, -1.217293),
+        (0.405170, -1.180473),
+        (0.245848, -1.129844),
+        (0.050371, -1.183813),
+        (-0.012215, -1.208861),
+        (-0.132870, -1.126613),
+        (-0.438455, -0.774861),
+        (-0.533425, -0.619174),
+        (-0.648444, -0.359265),
+        (-0.841035, 0.148204),
+        (-0.842563, 0.275554),
+        (-0.844664, 0.450661),
+        (-0.739898, 0.677965),
+        (-0.675930, 0.722019),
+        (-0.587975, 0.782593),
+        (-0.318740, 0.724074),
+        (-0.223744, 0.700792),
+    ];
+
+    #[cfg(feature = "mock-core")]
+    #[test]
+    fn reference_output() {
+        use core::mock::{MockMoc, MockParameter};
+        use mdl::Model;
+
+        let mut desc = MockMoc::sample();
+        desc.parameters
+            .push(MockParameter::new("ParamSway", -10.0, 10.0, 0.0));
+        let mut model = Model::from_bytes(&desc.to_bytes()).unwrap();
+        let rig = PHYSICS3.replace("ParamMissing", "ParamSway");
+        let mut physics = Physics::from_slice(rig.as_bytes()).unwrap();
+        // the input jumps between three angles while the time steps don't line up with the 30 fps
+        // of the rig, so the inputs and outputs are interpolated
+        let deltas = [0.011, 0.023, 0.008];
+        for (i, &(arm, sway)) in REFERENCE.iter().enumerate() {
+            let angle = match i {
+                0..=14 => 30.0,
+                15..=29 => -20.0,
+                _ => 0.0,
+            };
+            model.set_parameter_value("ParamAngleX", angle);
+            physics.evaluate(&mut model, deltas[i % 3]);
+            let result = (
+                model.parameter_value("ParamArmX"),
+                model.parameter_value("ParamSway"),
+            );
+            assert!(
+                (result.0 - arm).abs() < 1e-4 && (result.1 - sway).abs() < 1e-4,
+                "{:?} != {:?} after step {}",
+                result,
+                (arm, sway),
+                i
+            );
+        }
+    }
+
+    #[cfg(feature = "mock-core")]
+    #[test]
+    fn fixed_steps() {
+        use core::mock::MockMoc;
+        use mdl::Model;
+
+        // the same time split up differently leads to the same state
+        let mut models = [
+            Model::from_bytes(&MockMoc::sample().to_bytes()).unwrap(),
+            Model::from_bytes(&MockMoc::sample().to_bytes()).unwrap(),
+        ];
+        let mut physics = [
+            Physics::from_slice(PHYSICS3.as_bytes()).unwrap(),
+            Physics::from_slice(PHYSICS3.as_bytes()).unwrap(),
+        ];
+        for model in &mut models {
+            model.set_parameter_value("ParamAngleX", 30.0);
+        }
+        for _ in 0..3 {
+            physics[0].evaluate(&mut models[0], 1.0 / 30.0);
+            physics[1].evaluate(&mut models[1], 1.0 / 60.0);
+            physics[1].evaluate(&mut models[1], 1.0 / 60.0);
+        }
+        assert_eq!(
+            models[0].parameter_value("ParamArmX"),
+            models[1].parameter_value("ParamArmX")
+        );
+    }
+}
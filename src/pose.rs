@@ -0,0 +1,325 @@
+//! The pose3.json file and the switching between alternative parts
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use serde_json;
+
+use mdl::Model;
+use {CubismError, Result};
+
+const EPSILON: f32 = 0.001;
+// the opacity of the newly shown part at which the hidden parts start fading out faster
+const PHI: f32 = 0.5;
+// the maximum opacity the background may show through with
+const BACK_OPACITY_THRESHOLD: f32 = 0.15;
+
+/// A part of a pose group.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct PosePart {
+    /// The id of the part.
+    pub id: String,
+    /// The ids of the parts that always have the same opacity as this one.
+    #[serde(rename = "Link", default)]
+    pub links: Vec<String>,
+}
+
+/// A pose loaded from a pose3.json file, making sure only one part of every group is visible.
+///
+/// The visible part of a group is the first one with a visibility above 0.001. It fades in over
+/// `fade_in_time` while the other parts of its group fade out, linked parts are given the opacity of
+/// the part they are linked to.
+///
+/// The visibility of a part is the value of the parameter with the same id as the part, a virtual
+/// parameter if the moc has none, see
+/// [virtual_parameter_value](./struct.Model.html#method.virtual_parameter_value). Motions switch parts
+/// by writing their part opacity curves into these parameters.
+#[derive(Clone, Debug)]
+pub struct Pose {
+    /// The time it takes to switch between parts in seconds.
+    pub fade_in_time: f32,
+    /// The groups of alternative parts, the first part of a group is visible after a reset.
+    pub groups: Vec<Vec<PosePart>>,
+    needs_reset: bool,
+}
+
+impl Pose {
+    /// Loads the pose3.json file at `path`.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_reader(&mut File::open(path)?)
+    }
+
+    /// Parses a pose from a reader instance.
+    pub fn from_reader<R: Read>(reader: &mut R) -> Result<Self> {
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf)?;
+        Self::from_slice(&buf)
+    }
+
+    /// Parses a pose from json data, a missing fade time defaults to 0.5 seconds.
+    pub fn from_slice(data: &[u8]) -> Result<Self> {
+        let json: Pose3Json = serde_json::from_slice(data)?;
+        if json.kind != "Live2D Pose" {
+            return Err(CubismError::InvalidJson(format!(
+                "unsupported pose3.json type {}",
+                json.kind
+            )));
+        }
+        let fade_in_time = json.fade_in_time.unwrap_or(0.5);
+        if fade_in_time < 0.0 || fade_in_time.is_nan() {
+            return Err(CubismError::InvalidJson(format!(
+                "invalid fade time {}",
+                fade_in_time
+            )));
+        }
+        Ok(Pose {
+            fade_in_time,
+            groups: json.groups,
+            needs_reset: true,
+        })
+    }
+
+    /// Shows the first part of every group and hides the others, setting their parameters accordingly.
+    ///
+    /// This happens automatically on the first [update](#method.update).
+    pub fn reset(&mut self, model: &mut Model) {
+        self.needs_reset = false;
+        for group in &self.groups {
+            for (i, part) in group.iter().enumerate() {
+                let value = if i == 0 { 1.0 } else { 0.0 };
+                if let Some(idx) = model.part_index(&*part.id) {
+                    model.part_opacities_mut()[idx.0] = value;
+                    model.set_virtual_parameter_value(&part.id, value);
+                }
+            }
+        }
+        self.copy_links(model);
+    }
+
+    /// Fades the parts of every group by `delta` seconds towards the part selected by the parameters,
+    /// then copies the opacities to the linked parts.
+    pub fn update(&mut self, model: &mut Model, delta: f32) {
+        if self.needs_reset {
+            self.reset(model);
+        }
+        let delta = delta.max(0.0);
+        for group in &self.groups {
+            self.fade_group(model, group, delta);
+        }
+        self.copy_links(model);
+    }
+
+    fn fade_group(&self, model: &mut Model, group: &[PosePart], delta: f32) {
+        let visible = group
+            .iter()
+            .position(|part| visibility(model, &part.id) > EPSILON);
+        let (visible, opacity) = match visible {
+            Some(visible) => {
+                let opacity = match model.part_index(&*group[visible].id) {
+                    Some(idx) if self.fade_in_time > 0.0 => {
+                        model.part_opacities()[idx.0] + delta / self.fade_in_time
+                    }
+                    _ => 1.0,
+                };
+                (visible, opacity.min(1.0))
+            }
+            None => (0, 1.0),
+        };
+
+        // the hidden parts fade out slower than the visible one fades in, but not so slow that the
+        // background shows through
+        let mut hidden_limit = if opacity < PHI {
+            opacity * (PHI - 1.0) / PHI + 1.0
+        } else {
+            (1.0 - opacity) * PHI / (1.0 - PHI)
+        };
+        let back_opacity = (1.0 - hidden_limit) * (1.0 - opacity);
+        if back_opacity > BACK_OPACITY_THRESHOLD {
+            hidden_limit = 1.0 - BACK_OPACITY_THRESHOLD / (1.0 - opacity);
+        }
+        for (i, part) in group.iter().enumerate() {
+            if let Some(idx) = model.part_index(&*part.id) {
+                let part_opacity = &mut model.part_opacities_mut()[idx.0];
+                if i == visible {
+                    *part_opacity = opacity;
+                } else if *part_opacity > hidden_limit {
+                    *part_opacity = hidden_limit;
+                }
+            }
+        }
+    }
+
+    fn copy_links(&self, model: &mut Model) {
+        for part in self.groups.iter().flatten() {
+            let opacity = match model.part_index(&*part.id) {
+                Some(idx) => model.part_opacities()[idx.0],
+                None => continue,
+            };
+            for link in &part.links {
+                if let Some(idx) = model.part_index(&**link) {
+                    model.part_opacities_mut()[idx.0] = opacity;
+                }
+            }
+        }
+    }
+}
+
+fn visibility(model: &Model, id: &str) -> f32 {
+    model.virtual_parameter_value(id).unwrap_or(0.0)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Pose3Json {
+    #[serde(rename = "Type")]
+    kind: String,
+    #[serde(default)]
+    fade_in_time: Option<f32>,
+    groups: Vec<Vec<PosePart>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Pose;
+    use CubismError;
+
+    const POSE3: &str = r#"{
+        "Type": "Live2D Pose",
+        "FadeInTime": 0.5,
+        "Groups": [
+            [
+                {"Id": "PartArmA", "Link": ["PartHandA"]},
+                {"Id": "PartArmB", "Link": []},
+                {"Id": "PartMissing", "Link": []}
+            ]
+        ]
+    }"#;
+
+    #[test]
+    fn pose_from_json() {
+        let pose = Pose::from_slice(POSE3.as_bytes()).unwrap();
+        assert_eq!(pose.fade_in_time, 0.5);
+        assert_eq!(pose.groups.len(), 1);
+        assert_eq!(pose.groups[0][0].links, &["PartHandA"]);
+        assert!(pose.groups[0][1].links.is_empty());
+
+        let without_fade = POSE3.replace(r#""FadeInTime": 0.5,"#, "");
+        assert_eq!(
+            Pose::from_slice(without_fade.as_bytes())
+                .unwrap()
+                .fade_in_time,
+            0.5
+        );
+        let invalid = [
+            POSE3.replace("Live2D Pose", "Live2D Expression"),
+            POSE3.replace(r#""FadeInTime": 0.5"#, r#""FadeInTime": -1"#),
+        ];
+        for json in &invalid {
+            match Pose::from_slice(json.as_bytes()) {
+                Err(CubismError::InvalidJson(_)) => (),
+                other => panic!("unexpected result {:?}", other),
+            }
+        }
+    }
+
+    #[cfg(feature = "mock-core")]
+    #[test]
+    fn switch_parts() {
+        use anim::Motion;
+        use core::mock::{MockMoc, MockPart};
+        use mdl::Model;
+
+        let mut desc = MockMoc::sample();
+        for id in &["PartArmA", "PartArmB", "PartHandA"] {
+            desc.parts.push(MockPart::new(id));
+        }
+        let mut model = Model::from_bytes(&desc.to_bytes()).unwrap();
+        let mut pose = Pose::from_slice(POSE3.as_bytes()).unwrap();
+        let opacities = |model: &Model| {
+            (
+                model.part_opacity("PartArmA"),
+                model.part_opacity("PartHandA"),
+                model.part_opacity("PartArmB"),
+            )
+        };
+        let assert_near = |model: &Model, (a, b, c): (f32, f32, f32)| {
+            let (arm_a, hand_a, arm_b) = opacities(model);
+            assert!(
+                (arm_a - a).abs() < 1e-6 && (hand_a - b).abs() < 1e-6 && (arm_b - c).abs() < 1e-6,
+                "{:?} != {:?}",
+                (arm_a, hand_a, arm_b),
+                (a, b, c)
+            );
+        };
+
+        // the first update resets the model to the first part of the group
+        model.set_part_opacity("PartArmB", 1.0);
+        pose.update(&mut model, 0.0);
+        assert_eq!(opacities(&model), (1.0, 1.0, 0.0));
+        assert_eq!(model.virtual_parameter_value("PartArmA"), Some(1.0));
+        assert_eq!(model.virtual_parameter_value("PartArmB"), Some(0.0));
+        pose.update(&mut model, 1.0);
+        assert_eq!(opacities(&model), (1.0, 1.0, 0.0));
+
+        // halfway through the switch the old part is kept opaque enough to hide the background
+        model.set_virtual_parameter_value("PartArmA", 0.0);
+        model.set_virtual_parameter_value("PartArmB", 1.0);
+        pose.update(&mut model, 0.25);
+        assert_near(&model, (0.7, 0.7, 0.5));
+        pose.update(&mut model, 0.25);
+        assert_eq!(opacities(&model), (0.0, 0.0, 1.0));
+
+        // the first visible part of a group wins
+        model.set_virtual_parameter_value("PartArmA", 1.0);
+        pose.update(&mut model, 1.0);
+        assert_eq!(opacities(&model), (1.0, 1.0, 0.0));
+
+        // a motion switches parts through their parameters, the pose fades between them
+        let motion = Motion::from_slice(
+            br#"{
+                "Version": 3,
+                "Meta": {"Duration": 1.0, "Fps": 30.0},
+                "Curves": [
+                    {"Target": "PartOpacity", "Id": "PartArmA", "Segments": [0, 0]},
+                    {"Target": "PartOpacity", "Id": "PartArmB", "Segments": [0, 1]}
+                ]
+            }"#,
+        )
+        .unwrap();
+        motion.apply(&mut model, 0.0, 1.0);
+        pose.update(&mut model, 0.1);
+        assert_near(&model, (0.8125, 0.8125, 0.2));
+        motion.apply(&mut model, 0.1, 1.0);
+        pose.update(&mut model, 0.1);
+        assert_near(&model, (0.75, 0.75, 0.4));
+        for _ in 0..3 {
+            motion.apply(&mut model, 0.2, 1.0);
+            pose.update(&mut model, 0.1);
+        }
+        assert_near(&model, (0.0, 0.0, 1.0));
+    }
+
+    #[cfg(feature = "mock-core")]
+    #[test]
+    fn switch_parts_with_parameters() {
+        use core::mock::{MockMoc, MockParameter, MockPart};
+        use mdl::Model;
+
+        let mut desc = MockMoc::sample();
+        for id in &["PartArmA", "PartArmB"] {
+            desc.parameters.push(MockParameter::new(id, 0.0, 1.0, 0.0));
+            desc.parts.push(MockPart::new(id));
+        }
+        let mut model = Model::from_bytes(&desc.to_bytes()).unwrap();
+        let mut pose = Pose::from_slice(POSE3.as_bytes()).unwrap();
+        pose.update(&mut model, 0.0);
+        assert_eq!(model.parameter_value("PartArmA"), 1.0);
+
+        model.set_parameter_value("PartArmA", 0.0);
+        model.set_parameter_value("PartArmB", 1.0);
+        pose.update(&mut model, 0.5);
+        assert_eq!(model.part_opacity("PartArmA"), 0.0);
+        assert_eq!(model.part_opacity("PartArmB"), 1.0);
+    }
+}
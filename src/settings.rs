@@ -0,0 +1,399 @@
+//! The model3.json settings file
+use std::collections::{BTreeMap, HashSet};
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use serde_json;
+
+use {CubismError, Result};
+
+/// The settings of a model as described by its model3.json file.
+///
+/// All file references are resolved against the directory the settings were loaded from,
+/// so they can be opened directly.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ModelSettings {
+    /// The version of the settings file format.
+    pub version: u32,
+    /// The moc3 file.
+    pub moc: PathBuf,
+    /// The texture files, indexed by the texture indices of the drawables.
+    pub textures: Vec<PathBuf>,
+    /// The physics3.json file.
+    pub physics: Option<PathBuf>,
+    /// The pose3.json file.
+    pub pose: Option<PathBuf>,
+    /// The userdata3.json file.
+    pub user_data: Option<PathBuf>,
+    /// The cdi3.json display info file.
+    pub display_info: Option<PathBuf>,
+    /// The expressions of the model.
+    pub expressions: Vec<ExpressionReference>,
+    /// The motions of the model by group name.
+    pub motions: BTreeMap<String, Vec<MotionReference>>,
+    /// Named groups of parameters or parts, like `EyeBlink` and `LipSync`.
+    pub groups: Vec<Group>,
+    /// The hit areas of the model.
+    pub hit_areas: Vec<HitArea>,
+    /// The layout of the model.
+    pub layout: Layout,
+}
+
+/// A reference to an exp3.json file.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ExpressionReference {
+    /// The name of the expression.
+    pub name: String,
+    /// The exp3.json file.
+    pub file: PathBuf,
+}
+
+/// A reference to a motion3.json file.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct MotionReference {
+    /// The motion3.json file.
+    pub file: PathBuf,
+    /// A sound file that should be played with the motion.
+    #[serde(default)]
+    pub sound: Option<PathBuf>,
+    /// Overrides the fade in time of the motion, in seconds.
+    #[serde(default)]
+    pub fade_in_time: Option<f32>,
+    /// Overrides the fade out time of the motion, in seconds.
+    #[serde(default)]
+    pub fade_out_time: Option<f32>,
+}
+
+/// What the ids of a [Group](struct.Group.html) refer to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+pub enum GroupTarget {
+    /// The ids are parameter ids.
+    Parameter,
+    /// The ids are part ids.
+    Part,
+}
+
+/// A named group of parameters or parts.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Group {
+    /// What the ids refer to.
+    pub target: GroupTarget,
+    /// The name of the group.
+    pub name: String,
+    /// The parameter or part ids.
+    pub ids: Vec<String>,
+}
+
+/// A named area of the model that can be hit tested.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct HitArea {
+    /// The id of the drawable that makes up the area.
+    pub id: String,
+    /// The name of the area.
+    pub name: String,
+}
+
+/// How the model should be placed, all values are in model units.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Layout {
+    /// The horizontal center of the model.
+    pub center_x: Option<f32>,
+    /// The vertical center of the model.
+    pub center_y: Option<f32>,
+    /// The left edge of the model.
+    pub x: Option<f32>,
+    /// The top edge of the model.
+    pub y: Option<f32>,
+    /// The width of the model.
+    pub width: Option<f32>,
+    /// The height of the model.
+    pub height: Option<f32>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Model3Json {
+    version: u32,
+    file_references: FileReferences,
+    #[serde(default)]
+    groups: Vec<Group>,
+    #[serde(default)]
+    hit_areas: Vec<HitArea>,
+    #[serde(default)]
+    layout: Layout,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct FileReferences {
+    moc: PathBuf,
+    #[serde(default)]
+    textures: Vec<PathBuf>,
+    #[serde(default)]
+    physics: Option<PathBuf>,
+    #[serde(default)]
+    pose: Option<PathBuf>,
+    #[serde(default)]
+    user_data: Option<PathBuf>,
+    #[serde(default)]
+    display_info: Option<PathBuf>,
+    #[serde(default)]
+    expressions: Vec<ExpressionReference>,
+    #[serde(default)]
+    motions: BTreeMap<String, Vec<MotionReference>>,
+}
+
+impl ModelSettings {
+    /// The name of the group that contains the eye blink parameters.
+    pub const EYE_BLINK_GROUP: &'static str = "EyeBlink";
+    /// The name of the group that contains the lip sync parameters.
+    pub const LIP_SYNC_GROUP: &'static str = "LipSync";
+
+    /// Loads the settings from the model3.json file at `path`, file references are resolved against the
+    /// directory of the file.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+        Self::from_reader(&mut File::open(path)?, base_dir)
+    }
+
+    /// Parses the settings from a reader instance, file references are resolved against `base_dir`.
+    pub fn from_reader<R: Read>(reader: &mut R, base_dir: &Path) -> Result<Self> {
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf)?;
+        Self::from_slice(&buf, base_dir)
+    }
+
+    /// Parses the settings from json data, file references are resolved against `base_dir`.
+    pub fn from_slice(data: &[u8], base_dir: &Path) -> Result<Self> {
+        let json: Model3Json = serde_json::from_slice(data)?;
+        let refs = json.file_references;
+        let mut settings = ModelSettings {
+            version: json.version,
+            moc: refs.moc,
+            textures: refs.textures,
+            physics: refs.physics,
+            pose: refs.pose,
+            user_data: refs.user_data,
+            display_info: refs.display_info,
+            expressions: refs.expressions,
+            motions: refs.motions,
+            groups: json.groups,
+            hit_areas: json.hit_areas,
+            layout: json.layout,
+        };
+        settings.validate()?;
+        settings.resolve(base_dir);
+        Ok(settings)
+    }
+
+    /// Returns the group called `name`.
+    pub fn group(&self, name: &str) -> Option<&Group> {
+        self.groups.iter().find(|group| group.name == name)
+    }
+
+    /// Returns the ids of the parameters used for blinking.
+    pub fn eye_blink_parameter_ids(&self) -> &[String] {
+        self.parameter_group_ids(Self::EYE_BLINK_GROUP)
+    }
+
+    /// Returns the ids of the parameters used for lip syncing.
+    pub fn lip_sync_parameter_ids(&self) -> &[String] {
+        self.parameter_group_ids(Self::LIP_SYNC_GROUP)
+    }
+
+    fn parameter_group_ids(&self, name: &str) -> &[String] {
+        match self.group(name) {
+            Some(group) if group.target == GroupTarget::Parameter => &group.ids,
+            _ => &[],
+        }
+    }
+
+    /// Returns the expression called `name`.
+    pub fn expression(&self, name: &str) -> Option<&ExpressionReference> {
+        self.expressions.iter().find(|exp| exp.name == name)
+    }
+
+    /// Returns the hit area called `name`.
+    pub fn hit_area(&self, name: &str) -> Option<&HitArea> {
+        self.hit_areas.iter().find(|area| area.name == name)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.version != 3 {
+            return Err(invalid(format!(
+                "unsupported model3.json version {}",
+                self.version
+            )));
+        }
+        let paths = Some(&self.moc)
+            .into_iter()
+            .chain(&self.textures)
+            .chain(&self.physics)
+            .chain(&self.pose)
+            .chain(&self.user_data)
+            .chain(&self.display_info)
+            .chain(self.expressions.iter().map(|exp| &exp.file))
+            .chain(self.motions.values().flat_map(|motions| {
+                motions
+                    .iter()
+                    .flat_map(|motion| Some(&motion.file).into_iter().chain(&motion.sound))
+            }));
+        for path in paths {
+            if path.as_os_str().is_empty() {
+                return Err(invalid("empty file reference".to_owned()));
+            }
+            if path.has_root() {
+                return Err(invalid(format!(
+                    "file reference {} is not relative",
+                    path.display()
+                )));
+            }
+        }
+        let mut names = HashSet::new();
+        for exp in &self.expressions {
+            if !names.insert(&exp.name) {
+                return Err(invalid(format!("duplicate expression {}", exp.name)));
+            }
+        }
+        for motion in self.motions.values().flatten() {
+            let fades = motion.fade_in_time.into_iter().chain(motion.fade_out_time);
+            for fade in fades {
+                if fade < 0.0 || fade.is_nan() {
+                    return Err(invalid(format!(
+                        "invalid fade time {} for motion {}",
+                        fade,
+                        motion.file.display()
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn resolve(&mut self, base_dir: &Path) {
+        let resolve = |path: &mut PathBuf| *path = base_dir.join(&*path);
+        resolve(&mut self.moc);
+        self.textures.iter_mut().for_each(resolve);
+        self.physics.iter_mut().for_each(resolve);
+        self.pose.iter_mut().for_each(resolve);
+        self.user_data.iter_mut().for_each(resolve);
+        self.display_info.iter_mut().for_each(resolve);
+        for exp in &mut self.expressions {
+            resolve(&mut exp.file);
+        }
+        for motion in self.motions.values_mut().flatten() {
+            resolve(&mut motion.file);
+            motion.sound.iter_mut().for_each(resolve);
+        }
+    }
+}
+
+fn invalid(msg: String) -> CubismError {
+    CubismError::InvalidJson(msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GroupTarget, ModelSettings};
+    use std::path::Path;
+    use CubismError;
+
+    const MODEL3: &str = r#"{
+        "Version": 3,
+        "FileReferences": {
+            "Moc": "haru.moc3",
+            "Textures": ["haru.2048/texture_00.png", "haru.2048/texture_01.png"],
+            "Physics": "haru.physics3.json",
+            "Pose": "haru.pose3.json",
+            "Expressions": [
+                {"Name": "f00", "File": "expressions/F00.exp3.json"}
+            ],
+            "Motions": {
+                "Idle": [
+                    {"File": "motions/idle.motion3.json", "FadeInTime": 0.5, "Sound": "sounds/idle.wav"}
+                ],
+                "TapBody": [
+                    {"File": "motions/tap_body.motion3.json"}
+                ]
+            }
+        },
+        "Groups": [
+            {"Target": "Parameter", "Name": "EyeBlink", "Ids": ["ParamEyeLOpen", "ParamEyeROpen"]},
+            {"Target": "Parameter", "Name": "LipSync", "Ids": ["ParamMouthOpenY"]}
+        ],
+        "HitAreas": [{"Id": "HitAreaHead", "Name": "Head"}],
+        "Layout": {"CenterX": 0.0, "Width": 2.0}
+    }"#;
+
+    #[test]
+    fn settings_from_json() {
+        let settings =
+            ModelSettings::from_slice(MODEL3.as_bytes(), Path::new("assets/haru")).unwrap();
+        assert_eq!(settings.moc, Path::new("assets/haru/haru.moc3"));
+        assert_eq!(
+            settings.textures[1],
+            Path::new("assets/haru/haru.2048/texture_01.png")
+        );
+        assert_eq!(
+            settings.physics.as_ref().unwrap(),
+            Path::new("assets/haru/haru.physics3.json")
+        );
+        assert_eq!(settings.user_data, None);
+        assert_eq!(
+            settings.expression("f00").unwrap().file,
+            Path::new("assets/haru/expressions/F00.exp3.json")
+        );
+        let idle = &settings.motions["Idle"][0];
+        assert_eq!(
+            idle.file,
+            Path::new("assets/haru/motions/idle.motion3.json")
+        );
+        assert_eq!(
+            idle.sound.as_ref().unwrap(),
+            Path::new("assets/haru/sounds/idle.wav")
+        );
+        assert_eq!((idle.fade_in_time, idle.fade_out_time), (Some(0.5), None));
+        assert_eq!(settings.motions["TapBody"].len(), 1);
+        assert_eq!(
+            settings.eye_blink_parameter_ids(),
+            &["ParamEyeLOpen", "ParamEyeROpen"]
+        );
+        assert_eq!(settings.lip_sync_parameter_ids(), &["ParamMouthOpenY"]);
+        assert_eq!(settings.groups[0].target, GroupTarget::Parameter);
+        assert_eq!(settings.hit_area("Head").unwrap().id, "HitAreaHead");
+        assert_eq!(settings.layout.width, Some(2.0));
+        assert_eq!(settings.layout.height, None);
+    }
+
+    #[test]
+    fn invalid_settings() {
+        let base = Path::new("");
+        match ModelSettings::from_slice(b"{\"Version\": 3}", base) {
+            Err(CubismError::Json(_)) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+        let cases = [
+            MODEL3.replace("\"Version\": 3", "\"Version\": 4"),
+            MODEL3.replace("haru.moc3", ""),
+            MODEL3.replace("haru.pose3.json", "/haru.pose3.json"),
+            MODEL3.replace("\"FadeInTime\": 0.5", "\"FadeInTime\": -1.0"),
+            MODEL3.replace(
+                "\"Expressions\": [",
+                "\"Expressions\": [{\"Name\": \"f00\", \"File\": \"a.exp3.json\"},",
+            ),
+        ];
+        for json in &cases {
+            match ModelSettings::from_slice(json.as_bytes(), base) {
+                Err(CubismError::InvalidJson(_)) => (),
+                other => panic!("unexpected result {:?}", other),
+            }
+        }
+    }
+}